@@ -17,25 +17,297 @@
 SET NAMES utf8mb4;
 SET FOREIGN_KEY_CHECKS = 0;
 
+-- ----------------------------
+-- Table structure for account_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `account_tb`;
+CREATE TABLE `account_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `name` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '账户名',
+  `account_type` enum('cash','bank_card','credit_card','e_wallet') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '账户类型',
+  `opening_balance` decimal(12, 2) NOT NULL DEFAULT 0.00 COMMENT '期初余额',
+  `statement_day` int(11) NULL DEFAULT NULL COMMENT '信用卡账单日',
+  `due_day` int(11) NULL DEFAULT NULL COMMENT '信用卡还款日',
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `user_account_name`(`user_id`, `name`) USING BTREE
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for attachment_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `attachment_tb`;
+CREATE TABLE `attachment_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `bill_id` int(11) NOT NULL COMMENT '账单id',
+  `user_id` int(11) NOT NULL,
+  `file_name` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '上传时的文件名',
+  `mime` varchar(64) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '按文件头识别的类型',
+  `size` int(11) NOT NULL COMMENT '字节数',
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `attachment_bill_id`(`bill_id`) USING BTREE,
+  CONSTRAINT `attachment_bill_id` FOREIGN KEY (`bill_id`) REFERENCES `bill_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for bill_share_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `bill_share_tb`;
+CREATE TABLE `bill_share_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `bill_id` int(11) NOT NULL COMMENT '账单id',
+  `user_id` int(11) NOT NULL COMMENT '承担者用户id',
+  `amount` decimal(12, 2) NOT NULL COMMENT '承担金额，同一账单之和等于账单金额',
+  `shares` int(11) NULL DEFAULT NULL COMMENT '份数，按金额分摊时为空',
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `bill_user`(`bill_id`, `user_id`) USING BTREE,
+  INDEX `share_user_id`(`user_id`) USING BTREE,
+  CONSTRAINT `share_bill_id` FOREIGN KEY (`bill_id`) REFERENCES `bill_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for bill_split_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `bill_split_tb`;
+CREATE TABLE `bill_split_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `bill_id` int(11) NOT NULL COMMENT '账单id',
+  `tag_id` int(11) NOT NULL COMMENT '标签id',
+  `amount` decimal(12, 2) NOT NULL COMMENT '金额，同一账单的明细之和等于账单金额',
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `split_bill_id`(`bill_id`) USING BTREE,
+  INDEX `split_tag_id`(`tag_id`) USING BTREE,
+  CONSTRAINT `split_bill_id` FOREIGN KEY (`bill_id`) REFERENCES `bill_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT,
+  CONSTRAINT `split_tag_id` FOREIGN KEY (`tag_id`) REFERENCES `tag_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
 -- ----------------------------
 -- Table structure for bill_tb
 -- ----------------------------
 DROP TABLE IF EXISTS `bill_tb`;
 CREATE TABLE `bill_tb`  (
   `id` int(11) NOT NULL AUTO_INCREMENT,
-  `tag_id` int(11) NOT NULL COMMENT '标签id',
+  `tag_id` int(11) NULL DEFAULT NULL COMMENT '标签id，转账为空',
   `transaction_date` date NOT NULL,
   `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
   `created_time` datetime NOT NULL,
   `updated_time` datetime NOT NULL,
-  `pay_method` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '支付方式',
   `user_id` int(11) NOT NULL,
+  `ledger_id` int(11) NOT NULL COMMENT '账本id',
+  `payer_id` int(11) NULL DEFAULT NULL COMMENT '分摊账单的付款成员id，为空时为记账人',
   `pay` decimal(12, 2) NULL DEFAULT NULL,
+  `direction` enum('expense','income') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'expense' COMMENT '收支方向',
+  `recurring_id` int(11) NULL DEFAULT NULL COMMENT '周期账单规则id',
+  `external_id` varchar(128) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL DEFAULT NULL COMMENT '第三方账单交易号',
+  `account_id` int(11) NOT NULL COMMENT '账户id',
+  `transfer_id` int(11) NULL DEFAULT NULL COMMENT '转账id',
+  `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'CNY' COMMENT '币种，ISO 4217',
+  `reimbursable` tinyint(1) NOT NULL DEFAULT 0 COMMENT '是否为可报销的垫付支出',
+  `claim_id` int(11) NULL DEFAULT NULL COMMENT '报销单id',
+  `reimbursed_by` int(11) NULL DEFAULT NULL COMMENT '报销到账的收入账单id，不为空即已报销',
   PRIMARY KEY (`id`) USING BTREE,
   INDEX `tag_id`(`tag_id`) USING BTREE,
-  CONSTRAINT `tag_id` FOREIGN KEY (`tag_id`) REFERENCES `tag_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT
+  UNIQUE INDEX `recurring_date`(`recurring_id`, `transaction_date`) USING BTREE,
+  UNIQUE INDEX `user_external_id`(`user_id`, `external_id`) USING BTREE,
+  INDEX `account_id`(`account_id`) USING BTREE,
+  INDEX `transfer_id`(`transfer_id`) USING BTREE,
+  INDEX `ledger_id`(`ledger_id`) USING BTREE,
+  INDEX `claim_id`(`claim_id`) USING BTREE,
+  INDEX `reimbursed_by`(`reimbursed_by`) USING BTREE,
+  CONSTRAINT `tag_id` FOREIGN KEY (`tag_id`) REFERENCES `tag_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+  CONSTRAINT `account_id` FOREIGN KEY (`account_id`) REFERENCES `account_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+  CONSTRAINT `recurring_id` FOREIGN KEY (`recurring_id`) REFERENCES `recurring_tb` (`id`) ON DELETE SET NULL ON UPDATE RESTRICT,
+  CONSTRAINT `transfer_id` FOREIGN KEY (`transfer_id`) REFERENCES `transfer_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT,
+  CONSTRAINT `bill_ledger_id` FOREIGN KEY (`ledger_id`) REFERENCES `ledger_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+  CONSTRAINT `claim_id` FOREIGN KEY (`claim_id`) REFERENCES `claim_tb` (`id`) ON DELETE SET NULL ON UPDATE RESTRICT,
+  CONSTRAINT `reimbursed_by` FOREIGN KEY (`reimbursed_by`) REFERENCES `bill_tb` (`id`) ON DELETE SET NULL ON UPDATE RESTRICT
 ) ENGINE = InnoDB AUTO_INCREMENT = 2 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
 
+-- ----------------------------
+-- Table structure for budget_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `budget_tb`;
+CREATE TABLE `budget_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `tag_id` int(11) NULL DEFAULT NULL COMMENT '标签id，为空表示整个账本',
+  `period` enum('month','year') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '预算周期',
+  `amount` decimal(12, 2) NOT NULL COMMENT '预算金额',
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `budget_tag_id`(`tag_id`) USING BTREE,
+  CONSTRAINT `budget_tag_id` FOREIGN KEY (`tag_id`) REFERENCES `tag_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for claim_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `claim_tb`;
+CREATE TABLE `claim_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `name` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '报销单名称',
+  `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'CNY' COMMENT '币种，报销单内账单须为同一币种',
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `claim_user_id`(`user_id`) USING BTREE
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for debt_repayment_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `debt_repayment_tb`;
+CREATE TABLE `debt_repayment_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `debt_id` int(11) NOT NULL COMMENT '借贷id',
+  `bill_id` int(11) NOT NULL COMMENT '还款账单id，还款金额取账单金额',
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `repayment_bill_id`(`bill_id`) USING BTREE,
+  INDEX `repayment_debt_id`(`debt_id`) USING BTREE,
+  CONSTRAINT `repayment_debt_id` FOREIGN KEY (`debt_id`) REFERENCES `debt_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT,
+  CONSTRAINT `repayment_bill_id` FOREIGN KEY (`bill_id`) REFERENCES `bill_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for debt_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `debt_tb`;
+CREATE TABLE `debt_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `counterparty` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '对方姓名',
+  `direction` enum('lend','borrow') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT 'lend 借出，borrow 借入',
+  `principal` decimal(12, 2) NOT NULL COMMENT '本金',
+  `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'CNY' COMMENT '币种，ISO 4217',
+  `debt_date` date NOT NULL COMMENT '借款日期',
+  `due_date` date NULL DEFAULT NULL COMMENT '约定还款日期',
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `debt_user_id`(`user_id`) USING BTREE
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for exchange_rate_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `exchange_rate_tb`;
+CREATE TABLE `exchange_rate_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '币种，ISO 4217',
+  `rate_date` date NOT NULL COMMENT '生效日期',
+  `rate` decimal(18, 8) NOT NULL COMMENT '1 单位该币种折合人民币',
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `currency_date`(`currency`, `rate_date`) USING BTREE
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for ledger_invite_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `ledger_invite_tb`;
+CREATE TABLE `ledger_invite_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `ledger_id` int(11) NOT NULL COMMENT '账本id',
+  `code` char(8) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '邀请码',
+  `role` enum('owner','editor','viewer') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '加入后的角色，不会是 owner',
+  `expired_time` datetime NOT NULL COMMENT '过期时间',
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `code`(`code`) USING BTREE,
+  INDEX `invite_ledger_id`(`ledger_id`) USING BTREE,
+  CONSTRAINT `invite_ledger_id` FOREIGN KEY (`ledger_id`) REFERENCES `ledger_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for ledger_member_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `ledger_member_tb`;
+CREATE TABLE `ledger_member_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `ledger_id` int(11) NOT NULL COMMENT '账本id',
+  `user_id` int(11) NOT NULL,
+  `role` enum('owner','editor','viewer') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '角色：owner 管理账本与成员，editor 记账，viewer 只读',
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `ledger_user`(`ledger_id`, `user_id`) USING BTREE,
+  INDEX `member_user_id`(`user_id`) USING BTREE,
+  CONSTRAINT `member_ledger_id` FOREIGN KEY (`ledger_id`) REFERENCES `ledger_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for ledger_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `ledger_tb`;
+CREATE TABLE `ledger_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `name` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '账本名',
+  `owner_id` int(11) NOT NULL COMMENT '创建者用户id',
+  `personal` tinyint(1) NOT NULL DEFAULT 0 COMMENT '是否为注册时创建的个人账本，个人账本不能共享和删除',
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `owner_id`(`owner_id`) USING BTREE
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for recurring_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `recurring_tb`;
+CREATE TABLE `recurring_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `tag_id` int(11) NOT NULL COMMENT '标签id',
+  `pay` decimal(12, 2) NOT NULL,
+  `direction` enum('expense','income') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'expense' COMMENT '收支方向',
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `frequency` enum('daily','weekly','monthly','yearly') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '重复周期',
+  `start_date` date NOT NULL,
+  `end_date` date NULL DEFAULT NULL COMMENT '为空表示不结束',
+  `next_date` date NOT NULL COMMENT '下一次记账日期',
+  `account_id` int(11) NOT NULL COMMENT '账户id',
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'CNY' COMMENT '币种，ISO 4217',
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `recurring_tag_id`(`tag_id`) USING BTREE,
+  INDEX `next_date`(`next_date`) USING BTREE,
+  INDEX `recurring_account_id`(`account_id`) USING BTREE,
+  CONSTRAINT `recurring_account_id` FOREIGN KEY (`account_id`) REFERENCES `account_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+  CONSTRAINT `recurring_tag_id` FOREIGN KEY (`tag_id`) REFERENCES `tag_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- ----------------------------
+-- Table structure for settlement_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `settlement_tb`;
+CREATE TABLE `settlement_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `ledger_id` int(11) NOT NULL COMMENT '账本id',
+  `user_id` int(11) NOT NULL COMMENT '记录人用户id',
+  `from_user_id` int(11) NOT NULL COMMENT '付款成员id',
+  `to_user_id` int(11) NOT NULL COMMENT '收款成员id',
+  `amount` decimal(12, 2) NOT NULL,
+  `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '币种，ISO 4217',
+  `settle_date` date NOT NULL,
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `settlement_ledger_id`(`ledger_id`) USING BTREE,
+  CONSTRAINT `settlement_ledger_id` FOREIGN KEY (`ledger_id`) REFERENCES `ledger_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
 -- ----------------------------
 -- Table structure for tag_tb
 -- ----------------------------
@@ -46,9 +318,39 @@ CREATE TABLE `tag_tb`  (
   `created_time` datetime NOT NULL,
   `updated_time` datetime NOT NULL,
   `user_id` int(11) NOT NULL,
-  PRIMARY KEY (`id`) USING BTREE
+  `ledger_id` int(11) NOT NULL COMMENT '账本id',
+  `parent_id` int(11) NULL DEFAULT NULL COMMENT '上级标签id，仅支持两级',
+  `icon` varchar(64) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL DEFAULT NULL COMMENT '图标名',
+  `color` varchar(7) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL DEFAULT NULL COMMENT '颜色，#RRGGBB',
+  `position` int(11) NOT NULL DEFAULT 0 COMMENT '排序，升序',
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `parent_id`(`parent_id`) USING BTREE,
+  INDEX `tag_ledger_id`(`ledger_id`) USING BTREE,
+  CONSTRAINT `tag_parent_id` FOREIGN KEY (`parent_id`) REFERENCES `tag_tb` (`id`) ON DELETE SET NULL ON UPDATE RESTRICT,
+  CONSTRAINT `tag_ledger_id` FOREIGN KEY (`ledger_id`) REFERENCES `ledger_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
 ) ENGINE = InnoDB AUTO_INCREMENT = 2 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
 
+-- ----------------------------
+-- Table structure for transfer_tb
+-- ----------------------------
+DROP TABLE IF EXISTS `transfer_tb`;
+CREATE TABLE `transfer_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `from_account_id` int(11) NOT NULL COMMENT '转出账户id',
+  `to_account_id` int(11) NOT NULL COMMENT '转入账户id',
+  `amount` decimal(12, 2) NOT NULL,
+  `transaction_date` date NOT NULL,
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `from_account_id`(`from_account_id`) USING BTREE,
+  INDEX `to_account_id`(`to_account_id`) USING BTREE,
+  CONSTRAINT `from_account_id` FOREIGN KEY (`from_account_id`) REFERENCES `account_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+  CONSTRAINT `to_account_id` FOREIGN KEY (`to_account_id`) REFERENCES `account_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
 -- ----------------------------
 -- Table structure for user_tb
 -- ----------------------------
@@ -59,6 +361,7 @@ CREATE TABLE `user_tb`  (
   `pass` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL,
   `created_time` datetime NOT NULL,
   `updated_time` datetime NOT NULL,
+  `base_currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'CNY' COMMENT '本位币，汇总金额按此折算',
   PRIMARY KEY (`id`) USING BTREE
 ) ENGINE = InnoDB AUTO_INCREMENT = 2 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
 
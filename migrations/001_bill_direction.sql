@@ -0,0 +1,3 @@
+-- 账单区分收入与支出，已有账单均视为支出
+ALTER TABLE `bill_tb`
+  ADD COLUMN `direction` enum('expense','income') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'expense' COMMENT '收支方向' AFTER `pay`;
@@ -0,0 +1,13 @@
+-- 按标签或整个账本设置月度/年度预算
+CREATE TABLE `budget_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `tag_id` int(11) NULL DEFAULT NULL COMMENT '标签id，为空表示整个账本',
+  `period` enum('month','year') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '预算周期',
+  `amount` decimal(12, 2) NOT NULL COMMENT '预算金额',
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `budget_tag_id`(`tag_id`) USING BTREE,
+  CONSTRAINT `budget_tag_id` FOREIGN KEY (`tag_id`) REFERENCES `tag_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
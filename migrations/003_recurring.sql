@@ -0,0 +1,25 @@
+-- 周期账单规则，由后台任务按期生成账单
+CREATE TABLE `recurring_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `tag_id` int(11) NOT NULL COMMENT '标签id',
+  `pay` decimal(12, 2) NOT NULL,
+  `direction` enum('expense','income') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'expense' COMMENT '收支方向',
+  `pay_method` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '支付方式',
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `frequency` enum('daily','weekly','monthly','yearly') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '重复周期',
+  `start_date` date NOT NULL,
+  `end_date` date NULL DEFAULT NULL COMMENT '为空表示不结束',
+  `next_date` date NOT NULL COMMENT '下一次记账日期',
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `recurring_tag_id`(`tag_id`) USING BTREE,
+  INDEX `next_date`(`next_date`) USING BTREE,
+  CONSTRAINT `recurring_tag_id` FOREIGN KEY (`tag_id`) REFERENCES `tag_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+ALTER TABLE `bill_tb`
+  ADD COLUMN `recurring_id` int(11) NULL DEFAULT NULL COMMENT '周期账单规则id' AFTER `direction`,
+  ADD UNIQUE INDEX `recurring_date`(`recurring_id`, `transaction_date`) USING BTREE,
+  ADD CONSTRAINT `recurring_id` FOREIGN KEY (`recurring_id`) REFERENCES `recurring_tb` (`id`) ON DELETE SET NULL ON UPDATE RESTRICT;
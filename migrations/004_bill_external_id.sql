@@ -0,0 +1,4 @@
+-- 记录支付宝、微信账单的交易号，重复导入时据此跳过
+ALTER TABLE `bill_tb`
+  ADD COLUMN `external_id` varchar(128) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL DEFAULT NULL COMMENT '第三方账单交易号' AFTER `recurring_id`,
+  ADD UNIQUE INDEX `user_external_id`(`user_id`, `external_id`) USING BTREE;
@@ -0,0 +1,70 @@
+-- 支付方式改为账户，原有 pay_method 文本按名称合并为账户
+CREATE TABLE `account_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `name` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '账户名',
+  `account_type` enum('cash','bank_card','credit_card','e_wallet') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '账户类型',
+  `opening_balance` decimal(12, 2) NOT NULL DEFAULT 0.00 COMMENT '期初余额',
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `user_account_name`(`user_id`, `name`) USING BTREE
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- 常见的同义写法归并为同一个账户，其余按原文（排序规则不区分大小写）
+CREATE TEMPORARY TABLE `pay_method_tmp` AS
+SELECT DISTINCT
+  t.`user_id`,
+  t.`pay_method`,
+  CASE
+    WHEN LOWER(TRIM(t.`pay_method`)) IN ('微信', '微信支付', 'wechat', 'wechat pay', 'weixin') THEN '微信'
+    WHEN LOWER(TRIM(t.`pay_method`)) IN ('支付宝', 'alipay') THEN '支付宝'
+    WHEN LOWER(TRIM(t.`pay_method`)) IN ('现金', 'cash') THEN '现金'
+    WHEN TRIM(t.`pay_method`) = '' THEN '未知账户'
+    ELSE TRIM(t.`pay_method`)
+  END AS `name`
+FROM (
+  SELECT `user_id`, `pay_method` FROM `bill_tb`
+  UNION
+  SELECT `user_id`, `pay_method` FROM `recurring_tb`
+) t;
+
+INSERT INTO `account_tb` (`user_id`, `name`, `account_type`, `opening_balance`, `created_time`, `updated_time`)
+SELECT
+  `user_id`,
+  `name`,
+  CASE
+    WHEN `name` = '现金' THEN 'cash'
+    WHEN `name` LIKE '%信用卡%' THEN 'credit_card'
+    WHEN `name` LIKE '%银行%' OR `name` LIKE '%储蓄卡%' OR `name` LIKE '%借记卡%' THEN 'bank_card'
+    ELSE 'e_wallet'
+  END,
+  0.00,
+  NOW(),
+  NOW()
+FROM `pay_method_tmp`
+GROUP BY `user_id`, `name`;
+
+ALTER TABLE `bill_tb` ADD COLUMN `account_id` int(11) NULL DEFAULT NULL COMMENT '账户id' AFTER `external_id`;
+UPDATE `bill_tb` b
+  JOIN `pay_method_tmp` p ON p.`user_id` = b.`user_id` AND p.`pay_method` = b.`pay_method`
+  JOIN `account_tb` a ON a.`user_id` = p.`user_id` AND a.`name` = p.`name`
+SET b.`account_id` = a.`id`;
+ALTER TABLE `bill_tb`
+  MODIFY COLUMN `account_id` int(11) NOT NULL COMMENT '账户id',
+  ADD INDEX `account_id`(`account_id`) USING BTREE,
+  ADD CONSTRAINT `account_id` FOREIGN KEY (`account_id`) REFERENCES `account_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+  DROP COLUMN `pay_method`;
+
+ALTER TABLE `recurring_tb` ADD COLUMN `account_id` int(11) NULL DEFAULT NULL COMMENT '账户id' AFTER `next_date`;
+UPDATE `recurring_tb` r
+  JOIN `pay_method_tmp` p ON p.`user_id` = r.`user_id` AND p.`pay_method` = r.`pay_method`
+  JOIN `account_tb` a ON a.`user_id` = p.`user_id` AND a.`name` = p.`name`
+SET r.`account_id` = a.`id`;
+ALTER TABLE `recurring_tb`
+  MODIFY COLUMN `account_id` int(11) NOT NULL COMMENT '账户id',
+  ADD INDEX `recurring_account_id`(`account_id`) USING BTREE,
+  ADD CONSTRAINT `recurring_account_id` FOREIGN KEY (`account_id`) REFERENCES `account_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+  DROP COLUMN `pay_method`;
+
+DROP TEMPORARY TABLE `pay_method_tmp`;
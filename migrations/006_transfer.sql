@@ -0,0 +1,23 @@
+-- 账户间转账，生成一对关联的转出/转入账单，不计入收支统计
+CREATE TABLE `transfer_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `from_account_id` int(11) NOT NULL COMMENT '转出账户id',
+  `to_account_id` int(11) NOT NULL COMMENT '转入账户id',
+  `amount` decimal(12, 2) NOT NULL,
+  `transaction_date` date NOT NULL,
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `from_account_id`(`from_account_id`) USING BTREE,
+  INDEX `to_account_id`(`to_account_id`) USING BTREE,
+  CONSTRAINT `from_account_id` FOREIGN KEY (`from_account_id`) REFERENCES `account_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT,
+  CONSTRAINT `to_account_id` FOREIGN KEY (`to_account_id`) REFERENCES `account_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+ALTER TABLE `bill_tb`
+  MODIFY COLUMN `tag_id` int(11) NULL DEFAULT NULL COMMENT '标签id，转账为空',
+  ADD COLUMN `transfer_id` int(11) NULL DEFAULT NULL COMMENT '转账id' AFTER `account_id`,
+  ADD INDEX `transfer_id`(`transfer_id`) USING BTREE,
+  ADD CONSTRAINT `transfer_id` FOREIGN KEY (`transfer_id`) REFERENCES `transfer_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT;
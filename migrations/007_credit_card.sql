@@ -0,0 +1,4 @@
+-- 信用卡账户的账单日与还款日
+ALTER TABLE `account_tb`
+  ADD COLUMN `statement_day` int(11) NULL DEFAULT NULL COMMENT '信用卡账单日' AFTER `opening_balance`,
+  ADD COLUMN `due_day` int(11) NULL DEFAULT NULL COMMENT '信用卡还款日' AFTER `statement_day`;
@@ -0,0 +1,5 @@
+-- 标签分为两级，统计与预算中子标签的金额汇总到上级标签
+ALTER TABLE `tag_tb`
+  ADD COLUMN `parent_id` int(11) NULL DEFAULT NULL COMMENT '上级标签id，仅支持两级' AFTER `user_id`,
+  ADD INDEX `parent_id`(`parent_id`) USING BTREE,
+  ADD CONSTRAINT `tag_parent_id` FOREIGN KEY (`parent_id`) REFERENCES `tag_tb` (`id`) ON DELETE SET NULL ON UPDATE RESTRICT;
@@ -0,0 +1,8 @@
+-- 标签图标、颜色与自定义排序
+ALTER TABLE `tag_tb`
+  ADD COLUMN `icon` varchar(64) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL DEFAULT NULL COMMENT '图标名' AFTER `parent_id`,
+  ADD COLUMN `color` varchar(7) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL DEFAULT NULL COMMENT '颜色，#RRGGBB' AFTER `icon`,
+  ADD COLUMN `position` int(11) NOT NULL DEFAULT 0 COMMENT '排序，升序' AFTER `color`;
+
+-- 已有标签按创建顺序排列
+UPDATE `tag_tb` SET `position` = `id`;
@@ -0,0 +1,20 @@
+-- 多币种账单：账单、周期账单记录币种，用户设置本位币，汇率由管理员上传
+ALTER TABLE `bill_tb`
+  ADD COLUMN `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'CNY' COMMENT '币种，ISO 4217' AFTER `transfer_id`;
+
+ALTER TABLE `recurring_tb`
+  ADD COLUMN `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'CNY' COMMENT '币种，ISO 4217' AFTER `updated_time`;
+
+ALTER TABLE `user_tb`
+  ADD COLUMN `base_currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'CNY' COMMENT '本位币，汇总金额按此折算' AFTER `updated_time`;
+
+CREATE TABLE `exchange_rate_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '币种，ISO 4217',
+  `rate_date` date NOT NULL COMMENT '生效日期',
+  `rate` decimal(18, 8) NOT NULL COMMENT '1 单位该币种折合人民币',
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `currency_date`(`currency`, `rate_date`) USING BTREE
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
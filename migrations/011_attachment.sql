@@ -0,0 +1,13 @@
+-- 账单附件（小票照片、PDF），文件存放在本地目录，表中只记录元数据
+CREATE TABLE `attachment_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `bill_id` int(11) NOT NULL COMMENT '账单id',
+  `user_id` int(11) NOT NULL,
+  `file_name` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '上传时的文件名',
+  `mime` varchar(64) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '按文件头识别的类型',
+  `size` int(11) NOT NULL COMMENT '字节数',
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `attachment_bill_id`(`bill_id`) USING BTREE,
+  CONSTRAINT `attachment_bill_id` FOREIGN KEY (`bill_id`) REFERENCES `bill_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
@@ -0,0 +1,14 @@
+-- 账单拆分明细，统计与预算按明细各自的标签归类
+CREATE TABLE `bill_split_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `bill_id` int(11) NOT NULL COMMENT '账单id',
+  `tag_id` int(11) NOT NULL COMMENT '标签id',
+  `amount` decimal(12, 2) NOT NULL COMMENT '金额，同一账单的明细之和等于账单金额',
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `split_bill_id`(`bill_id`) USING BTREE,
+  INDEX `split_tag_id`(`tag_id`) USING BTREE,
+  CONSTRAINT `split_bill_id` FOREIGN KEY (`bill_id`) REFERENCES `bill_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT,
+  CONSTRAINT `split_tag_id` FOREIGN KEY (`tag_id`) REFERENCES `tag_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
@@ -0,0 +1,60 @@
+-- 账本：账单与标签归属于账本，多个用户可以加入同一账本共同记账
+CREATE TABLE `ledger_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `name` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '账本名',
+  `owner_id` int(11) NOT NULL COMMENT '创建者用户id',
+  `personal` tinyint(1) NOT NULL DEFAULT 0 COMMENT '是否为注册时创建的个人账本，个人账本不能共享和删除',
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `owner_id`(`owner_id`) USING BTREE
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+CREATE TABLE `ledger_member_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `ledger_id` int(11) NOT NULL COMMENT '账本id',
+  `user_id` int(11) NOT NULL,
+  `role` enum('owner','editor','viewer') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '角色：owner 管理账本与成员，editor 记账，viewer 只读',
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `ledger_user`(`ledger_id`, `user_id`) USING BTREE,
+  INDEX `member_user_id`(`user_id`) USING BTREE,
+  CONSTRAINT `member_ledger_id` FOREIGN KEY (`ledger_id`) REFERENCES `ledger_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+CREATE TABLE `ledger_invite_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `ledger_id` int(11) NOT NULL COMMENT '账本id',
+  `code` char(8) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '邀请码',
+  `role` enum('owner','editor','viewer') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '加入后的角色，不会是 owner',
+  `expired_time` datetime NOT NULL COMMENT '过期时间',
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `code`(`code`) USING BTREE,
+  INDEX `invite_ledger_id`(`ledger_id`) USING BTREE,
+  CONSTRAINT `invite_ledger_id` FOREIGN KEY (`ledger_id`) REFERENCES `ledger_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- 为已有用户创建个人账本，原有账单和标签归入其中
+INSERT INTO `ledger_tb` (`name`, `owner_id`, `personal`, `created_time`, `updated_time`)
+  SELECT '个人账本', `id`, 1, NOW(), NOW() FROM `user_tb`;
+INSERT INTO `ledger_member_tb` (`ledger_id`, `user_id`, `role`, `created_time`)
+  SELECT `id`, `owner_id`, 'owner', NOW() FROM `ledger_tb`;
+
+ALTER TABLE `bill_tb`
+  ADD COLUMN `ledger_id` int(11) NULL DEFAULT NULL COMMENT '账本id' AFTER `user_id`;
+ALTER TABLE `tag_tb`
+  ADD COLUMN `ledger_id` int(11) NULL DEFAULT NULL COMMENT '账本id' AFTER `user_id`;
+UPDATE `bill_tb` `b` INNER JOIN `ledger_tb` `l` ON `l`.`owner_id` = `b`.`user_id` AND `l`.`personal` = 1
+  SET `b`.`ledger_id` = `l`.`id`;
+UPDATE `tag_tb` `t` INNER JOIN `ledger_tb` `l` ON `l`.`owner_id` = `t`.`user_id` AND `l`.`personal` = 1
+  SET `t`.`ledger_id` = `l`.`id`;
+
+ALTER TABLE `bill_tb`
+  MODIFY COLUMN `ledger_id` int(11) NOT NULL COMMENT '账本id',
+  ADD INDEX `ledger_id`(`ledger_id`) USING BTREE,
+  ADD CONSTRAINT `bill_ledger_id` FOREIGN KEY (`ledger_id`) REFERENCES `ledger_tb` (`id`) ON DELETE RESTRICT ON UPDATE RESTRICT;
+ALTER TABLE `tag_tb`
+  MODIFY COLUMN `ledger_id` int(11) NOT NULL COMMENT '账本id',
+  ADD INDEX `tag_ledger_id`(`ledger_id`) USING BTREE,
+  ADD CONSTRAINT `tag_ledger_id` FOREIGN KEY (`ledger_id`) REFERENCES `ledger_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT;
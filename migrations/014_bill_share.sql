@@ -0,0 +1,30 @@
+-- 账单在账本成员之间分摊，记账人为付款人
+CREATE TABLE `bill_share_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `bill_id` int(11) NOT NULL COMMENT '账单id',
+  `user_id` int(11) NOT NULL COMMENT '承担者用户id',
+  `amount` decimal(12, 2) NOT NULL COMMENT '承担金额，同一账单之和等于账单金额',
+  `shares` int(11) NULL DEFAULT NULL COMMENT '份数，按金额分摊时为空',
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `bill_user`(`bill_id`, `user_id`) USING BTREE,
+  INDEX `share_user_id`(`user_id`) USING BTREE,
+  CONSTRAINT `share_bill_id` FOREIGN KEY (`bill_id`) REFERENCES `bill_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+-- 成员之间的结算，抵消分摊产生的欠款
+CREATE TABLE `settlement_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `ledger_id` int(11) NOT NULL COMMENT '账本id',
+  `user_id` int(11) NOT NULL COMMENT '记录人用户id',
+  `from_user_id` int(11) NOT NULL COMMENT '付款成员id',
+  `to_user_id` int(11) NOT NULL COMMENT '收款成员id',
+  `amount` decimal(12, 2) NOT NULL,
+  `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '币种，ISO 4217',
+  `settle_date` date NOT NULL,
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `settlement_ledger_id`(`ledger_id`) USING BTREE,
+  CONSTRAINT `settlement_ledger_id` FOREIGN KEY (`ledger_id`) REFERENCES `ledger_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
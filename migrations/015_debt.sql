@@ -0,0 +1,28 @@
+-- 与非用户之间的借入借出，还款关联到账单
+CREATE TABLE `debt_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `counterparty` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '对方姓名',
+  `direction` enum('lend','borrow') CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT 'lend 借出，borrow 借入',
+  `principal` decimal(12, 2) NOT NULL COMMENT '本金',
+  `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'CNY' COMMENT '币种，ISO 4217',
+  `debt_date` date NOT NULL COMMENT '借款日期',
+  `due_date` date NULL DEFAULT NULL COMMENT '约定还款日期',
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `debt_user_id`(`user_id`) USING BTREE
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+CREATE TABLE `debt_repayment_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `debt_id` int(11) NOT NULL COMMENT '借贷id',
+  `bill_id` int(11) NOT NULL COMMENT '还款账单id，还款金额取账单金额',
+  `created_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  UNIQUE INDEX `repayment_bill_id`(`bill_id`) USING BTREE,
+  INDEX `repayment_debt_id`(`debt_id`) USING BTREE,
+  CONSTRAINT `repayment_debt_id` FOREIGN KEY (`debt_id`) REFERENCES `debt_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT,
+  CONSTRAINT `repayment_bill_id` FOREIGN KEY (`bill_id`) REFERENCES `bill_tb` (`id`) ON DELETE CASCADE ON UPDATE RESTRICT
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
@@ -0,0 +1,21 @@
+-- 报销：垫付的支出标记为可报销并归入报销单，报销款到账后标记为已报销
+CREATE TABLE `claim_tb`  (
+  `id` int(11) NOT NULL AUTO_INCREMENT,
+  `user_id` int(11) NOT NULL,
+  `name` varchar(255) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL COMMENT '报销单名称',
+  `currency` char(3) CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NOT NULL DEFAULT 'CNY' COMMENT '币种，报销单内账单须为同一币种',
+  `comment` text CHARACTER SET utf8mb4 COLLATE utf8mb4_general_ci NULL,
+  `created_time` datetime NOT NULL,
+  `updated_time` datetime NOT NULL,
+  PRIMARY KEY (`id`) USING BTREE,
+  INDEX `claim_user_id`(`user_id`) USING BTREE
+) ENGINE = InnoDB AUTO_INCREMENT = 1 CHARACTER SET = utf8mb4 COLLATE = utf8mb4_general_ci ROW_FORMAT = Compact;
+
+ALTER TABLE `bill_tb`
+  ADD COLUMN `reimbursable` tinyint(1) NOT NULL DEFAULT 0 COMMENT '是否为可报销的垫付支出' AFTER `currency`,
+  ADD COLUMN `claim_id` int(11) NULL DEFAULT NULL COMMENT '报销单id' AFTER `reimbursable`,
+  ADD COLUMN `reimbursed_by` int(11) NULL DEFAULT NULL COMMENT '报销到账的收入账单id，不为空即已报销' AFTER `claim_id`,
+  ADD INDEX `claim_id`(`claim_id`) USING BTREE,
+  ADD INDEX `reimbursed_by`(`reimbursed_by`) USING BTREE,
+  ADD CONSTRAINT `claim_id` FOREIGN KEY (`claim_id`) REFERENCES `claim_tb` (`id`) ON DELETE SET NULL ON UPDATE RESTRICT,
+  ADD CONSTRAINT `reimbursed_by` FOREIGN KEY (`reimbursed_by`) REFERENCES `bill_tb` (`id`) ON DELETE SET NULL ON UPDATE RESTRICT;
@@ -0,0 +1,3 @@
+-- 分摊账单可以记录由其他成员付款
+ALTER TABLE `bill_tb`
+  ADD COLUMN `payer_id` int(11) NULL DEFAULT NULL COMMENT '分摊账单的付款成员id，为空时为记账人' AFTER `ledger_id`;
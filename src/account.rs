@@ -0,0 +1,542 @@
+use crate::bill::{direction_sum, flow_sum};
+use crate::error::*;
+use crate::orm::{
+    self,
+    model::{
+        prelude::*,
+        sea_orm_active_enums::{AccountType, Direction},
+        *,
+    },
+};
+use anyhow::anyhow;
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
+use rust_decimal::prelude::*;
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveEnum, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait,
+    IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect,
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+fn parse_account_type(account_type: &str) -> JsonResult<AccountType> {
+    match account_type {
+        "cash" => Ok(AccountType::Cash),
+        "bank_card" => Ok(AccountType::BankCard),
+        "credit_card" => Ok(AccountType::CreditCard),
+        "e_wallet" => Ok(AccountType::EWallet),
+        _ => Err(JsonErr::from_error(400, anyhow!("无效的账户类型"))),
+    }
+}
+
+/// 账单日、还款日限定为 1-28 日，每个月都存在
+fn parse_cycle_day(value: &str, name: &str) -> JsonResult<i32> {
+    value
+        .parse::<i32>()
+        .ok()
+        .filter(|d| (1..=28).contains(d))
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的{name}，须为 1-28")))
+}
+
+/// 导入账单时自动建账户，按名称推测类型
+pub(crate) fn guess_account_type(name: &str) -> AccountType {
+    if name == "现金" || name.eq_ignore_ascii_case("cash") {
+        AccountType::Cash
+    } else if name.contains("信用卡") {
+        AccountType::CreditCard
+    } else if ["银行", "储蓄卡", "借记卡"]
+        .iter()
+        .any(|k| name.contains(k))
+    {
+        AccountType::BankCard
+    } else {
+        AccountType::EWallet
+    }
+}
+
+/// 账户列表，余额 = 期初余额 + 流入 - 流出（含转账）
+#[handler]
+pub async fn account_list(
+    _req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let db = orm::get_dao()?;
+    let accounts = AccountTb::find()
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .order_by_asc(account_tb::Column::Id)
+        .all(db)
+        .await
+        .json_err()?;
+    let amounts = BillTb::find()
+        .select_only()
+        .column(bill_tb::Column::AccountId)
+        .column_as(flow_sum(Direction::Income, user_id), "income_amount")
+        .column_as(flow_sum(Direction::Expense, user_id), "expense_amount")
+        .filter(bill_tb::Column::UserId.eq(user_id))
+        .group_by(bill_tb::Column::AccountId)
+        .into_tuple::<(i32, Option<Decimal>, Option<Decimal>)>()
+        .all(db)
+        .await
+        .json_err()?
+        .into_iter()
+        .map(|(id, income, expense)| {
+            (
+                id,
+                (income.unwrap_or_default(), expense.unwrap_or_default()),
+            )
+        })
+        .collect::<HashMap<_, _>>();
+    let list = accounts
+        .into_iter()
+        .map(|account| {
+            let (income, expense) = amounts.get(&account.id).copied().unwrap_or_default();
+            json!({
+                "id":account.id,
+                "name":account.name,
+                "account_type":account.account_type.to_value(),
+                "opening_balance":account.opening_balance,
+                "statement_day":account.statement_day,
+                "due_day":account.due_day,
+                "income_amount":income,
+                "expense_amount":expense,
+                "balance":account.opening_balance + income - expense
+            })
+        })
+        .collect::<Vec<_>>();
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn add_account(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let name = req
+        .form::<String>("name")
+        .await
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到有效账户名")))?;
+    let account_type = req
+        .form::<String>("account_type")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到账户类型")))?;
+    let account_type = parse_account_type(&account_type)?;
+    let opening_balance = match req.form::<String>("opening_balance").await {
+        Some(v) => Decimal::from_str(&v)
+            .map_err(|e| JsonErr::from_error(400, anyhow!("无效的期初余额 {e}")))?,
+        None => Decimal::ZERO,
+    };
+    let statement_day = match req.form::<String>("statement_day").await {
+        Some(v) => Some(parse_cycle_day(&v, "账单日")?),
+        None => None,
+    };
+    let due_day = match req.form::<String>("due_day").await {
+        Some(v) => Some(parse_cycle_day(&v, "还款日")?),
+        None => None,
+    };
+    if account_type != AccountType::CreditCard && (statement_day.is_some() || due_day.is_some()) {
+        res_error(400, anyhow!("仅信用卡账户可设置账单日、还款日"))?;
+        return Ok(());
+    }
+    let db = orm::get_dao()?;
+    if AccountTb::find()
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .filter(account_tb::Column::Name.eq(&name))
+        .count(db)
+        .await
+        .json_err()?
+        != 0
+    {
+        res_error(400, anyhow!("账户已存在"))?;
+        return Ok(());
+    }
+    let mut info = account_tb::ActiveModel::new();
+    info.user_id = Set(user_id);
+    info.name = Set(name);
+    info.account_type = Set(account_type);
+    info.opening_balance = Set(opening_balance);
+    info.statement_day = Set(statement_day);
+    info.due_day = Set(due_day);
+    let now = Local::now().naive_local();
+    info.created_time = Set(now);
+    info.updated_time = Set(now);
+    info.insert(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"新增成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn update_account(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let account_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账户ID")))?;
+    let db = orm::get_dao()?;
+    let Some(account) = AccountTb::find()
+        .filter(account_tb::Column::Id.eq(account_id))
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+    else {
+        res_error(400, anyhow!("无效的账户"))?;
+        return Ok(());
+    };
+    let mut info = account.into_active_model();
+    if let Some(name) = req
+        .form::<String>("name")
+        .await
+        .map(|s| s.trim().to_owned())
+    {
+        if name.is_empty() {
+            res_error(400, anyhow!("未获取到有效账户名"))?;
+            return Ok(());
+        }
+        if AccountTb::find()
+            .filter(account_tb::Column::UserId.eq(user_id))
+            .filter(account_tb::Column::Name.eq(&name))
+            .filter(account_tb::Column::Id.ne(account_id))
+            .count(db)
+            .await
+            .json_err()?
+            != 0
+        {
+            res_error(400, anyhow!("账户已存在"))?;
+            return Ok(());
+        }
+        info.name = Set(name);
+    }
+    if let Some(account_type) = req.form::<String>("account_type").await {
+        info.account_type = Set(parse_account_type(&account_type)?);
+    }
+    if let Some(opening_balance) = req.form::<String>("opening_balance").await {
+        let opening_balance = Decimal::from_str(&opening_balance)
+            .map_err(|e| JsonErr::from_error(400, anyhow!("无效的期初余额 {e}")))?;
+        info.opening_balance = Set(opening_balance);
+    }
+    let statement_day = req.form::<String>("statement_day").await;
+    let due_day = req.form::<String>("due_day").await;
+    if *info.account_type.as_ref() == AccountType::CreditCard {
+        if let Some(statement_day) = statement_day {
+            info.statement_day = Set(Some(parse_cycle_day(&statement_day, "账单日")?));
+        }
+        if let Some(due_day) = due_day {
+            info.due_day = Set(Some(parse_cycle_day(&due_day, "还款日")?));
+        }
+    } else if statement_day.is_some() || due_day.is_some() {
+        res_error(400, anyhow!("仅信用卡账户可设置账单日、还款日"))?;
+        return Ok(());
+    } else {
+        // 改为非信用卡账户时清空账单周期
+        info.statement_day = Set(None);
+        info.due_day = Set(None);
+    }
+    info.updated_time = Set(Local::now().naive_local());
+    info.update(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn del_account(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let account_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账户ID")))?;
+    let db = orm::get_dao()?;
+    let Some(account) = AccountTb::find()
+        .filter(account_tb::Column::Id.eq(account_id))
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+    else {
+        res_error(400, anyhow!("无效的账户"))?;
+        return Ok(());
+    };
+    let bill_count = BillTb::find()
+        .filter(bill_tb::Column::AccountId.eq(account_id))
+        .count(db)
+        .await
+        .json_err()?;
+    let recurring_count = RecurringTb::find()
+        .filter(recurring_tb::Column::AccountId.eq(account_id))
+        .count(db)
+        .await
+        .json_err()?;
+    if bill_count + recurring_count != 0 {
+        res_error(
+            400,
+            anyhow!("该账户仍有 {bill_count} 条账单、{recurring_count} 条周期账单，无法删除"),
+        )?;
+        return Ok(());
+    }
+    account.into_active_model().delete(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"删除成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 信用卡账单周期
+struct CreditCycle {
+    /// 已出账单的起止日期
+    begin: NaiveDate,
+    statement_date: NaiveDate,
+    due_date: NaiveDate,
+    /// 下一个账单日，当前未出账周期截止于此
+    next_statement_date: NaiveDate,
+}
+
+/// 以 `today` 当天或之前最近的账单日为已出账单，还款日早于等于账单日时落在次月
+fn credit_cycle_of(statement_day: u32, due_day: u32, today: NaiveDate) -> Option<CreditCycle> {
+    let mut statement_date = today.with_day(statement_day)?;
+    if today.day() < statement_day {
+        statement_date = statement_date.checked_sub_months(Months::new(1))?;
+    }
+    let begin = statement_date
+        .checked_sub_months(Months::new(1))?
+        .checked_add_days(Days::new(1))?;
+    let next_statement_date = statement_date.checked_add_months(Months::new(1))?;
+    let due_date = if due_day > statement_day {
+        statement_date.with_day(due_day)?
+    } else {
+        next_statement_date.with_day(due_day)?
+    };
+    Some(CreditCycle {
+        begin,
+        statement_date,
+        due_date,
+        next_statement_date,
+    })
+}
+
+/// 信用卡账单：已出账单的应还金额、账单日后转入的还款是否已还清，以及当前周期的消费
+#[handler]
+pub async fn credit_cycle(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let account_id = req
+        .query::<i32>("id")
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账户ID")))?;
+    let db = orm::get_dao()?;
+    let Some(account) = AccountTb::find()
+        .filter(account_tb::Column::Id.eq(account_id))
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+    else {
+        res_error(400, anyhow!("无效的账户"))?;
+        return Ok(());
+    };
+    let (Some(statement_day), Some(due_day)) = (account.statement_day, account.due_day) else {
+        res_error(400, anyhow!("该账户未设置账单日、还款日"))?;
+        return Ok(());
+    };
+    let today = Local::now().date_naive();
+    let cycle = credit_cycle_of(statement_day as u32, due_day as u32, today)
+        .ok_or(JsonErr::from_error(500, anyhow!("账单周期计算错误")))?;
+
+    // 消费与退款，不含转账
+    let cycle_sum = |begin: NaiveDate, end: NaiveDate| {
+        BillTb::find()
+            .select_only()
+            .column_as(direction_sum(Direction::Expense, user_id), "expense_amount")
+            .column_as(direction_sum(Direction::Income, user_id), "income_amount")
+            .filter(bill_tb::Column::AccountId.eq(account_id))
+            .filter(bill_tb::Column::TransactionDate.between(begin, end))
+            .into_tuple::<(Option<Decimal>, Option<Decimal>)>()
+            .one(db)
+    };
+    let (charges, refunds) = cycle_sum(cycle.begin, cycle.statement_date)
+        .await
+        .json_err()?
+        .unwrap_or_default();
+    let (charges, refunds) = (charges.unwrap_or_default(), refunds.unwrap_or_default());
+    let amount_due = (charges - refunds).max(Decimal::ZERO);
+    let (current_charges, current_refunds) = cycle_sum(
+        cycle.statement_date + Days::new(1),
+        cycle.next_statement_date,
+    )
+    .await
+    .json_err()?
+    .unwrap_or_default();
+
+    // 账单日之后、下一个账单日之前转入该卡的转账视为本期还款
+    let repaid = BillTb::find()
+        .select_only()
+        .column_as(flow_sum(Direction::Income, user_id), "repaid_amount")
+        .filter(bill_tb::Column::AccountId.eq(account_id))
+        .filter(bill_tb::Column::TransferId.is_not_null())
+        .filter(bill_tb::Column::TransactionDate.gt(cycle.statement_date))
+        .filter(bill_tb::Column::TransactionDate.lte(cycle.next_statement_date))
+        .into_tuple::<Option<Decimal>>()
+        .one(db)
+        .await
+        .json_err()?
+        .flatten()
+        .unwrap_or_default();
+    let remaining = (amount_due - repaid).max(Decimal::ZERO);
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "statement":{
+                        "begin":cycle.begin,
+                        "end":cycle.statement_date,
+                        "due_date":cycle.due_date,
+                        "charges":charges,
+                        "refunds":refunds,
+                        "amount_due":amount_due,
+                        "repaid":repaid,
+                        "remaining":remaining,
+                        "paid_off":remaining.is_zero(),
+                        "overdue":!remaining.is_zero() && today > cycle.due_date,
+                        "days_until_due":(cycle.due_date - today).num_days()
+                    },
+                    "current":{
+                        "begin":cycle.statement_date + Days::new(1),
+                        "end":cycle.next_statement_date,
+                        "charges":current_charges.unwrap_or_default(),
+                        "refunds":current_refunds.unwrap_or_default()
+                    }
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn cycle(statement_day: u32, due_day: u32, today: NaiveDate) -> [NaiveDate; 4] {
+        let cycle = credit_cycle_of(statement_day, due_day, today).unwrap();
+        [
+            cycle.begin,
+            cycle.statement_date,
+            cycle.due_date,
+            cycle.next_statement_date,
+        ]
+    }
+
+    #[test]
+    fn due_day_after_statement_day_same_month() {
+        assert_eq!(
+            cycle(5, 20, day(2024, 3, 10)),
+            [
+                day(2024, 2, 6),
+                day(2024, 3, 5),
+                day(2024, 3, 20),
+                day(2024, 4, 5)
+            ]
+        );
+    }
+
+    #[test]
+    fn before_statement_day_uses_previous_statement() {
+        assert_eq!(
+            cycle(10, 25, day(2024, 3, 5)),
+            [
+                day(2024, 1, 11),
+                day(2024, 2, 10),
+                day(2024, 2, 25),
+                day(2024, 3, 10)
+            ]
+        );
+    }
+
+    #[test]
+    fn statement_day_equal_to_due_day_falls_next_month() {
+        assert_eq!(
+            cycle(15, 15, day(2024, 3, 15)),
+            [
+                day(2024, 2, 16),
+                day(2024, 3, 15),
+                day(2024, 4, 15),
+                day(2024, 4, 15)
+            ]
+        );
+    }
+
+    #[test]
+    fn due_day_before_statement_day_crosses_year() {
+        assert_eq!(
+            cycle(20, 5, day(2024, 12, 25)),
+            [
+                day(2024, 11, 21),
+                day(2024, 12, 20),
+                day(2025, 1, 5),
+                day(2025, 1, 20)
+            ]
+        );
+    }
+}
@@ -0,0 +1,410 @@
+use crate::error::*;
+use crate::ledger::ledger_bill;
+use crate::orm::{
+    self,
+    model::{prelude::*, *},
+};
+use anyhow::anyhow;
+use chrono::Local;
+use salvo::fs::NamedFile;
+use salvo::http::Mime;
+use salvo::http::header::CONTENT_LENGTH;
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, DatabaseConnection,
+    DbErr, EntityTrait, IntoActiveModel, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::path::PathBuf;
+use std::sync::OnceLock;
+use tokio::io::AsyncReadExt;
+
+/// `config.toml` 中的 `[attachment]` 配置
+#[derive(Deserialize)]
+#[serde(default)]
+pub struct AttachmentConfig {
+    /// 附件存放目录，按用户分子目录
+    dir: String,
+    /// 单个文件大小上限，单位字节
+    max_size: u64,
+}
+
+impl Default for AttachmentConfig {
+    fn default() -> Self {
+        Self {
+            dir: "./attachments".to_owned(),
+            max_size: 10 * 1024 * 1024,
+        }
+    }
+}
+
+static CONFIG: OnceLock<AttachmentConfig> = OnceLock::new();
+
+/// 单次上传的文件个数上限
+const MAX_FILES: usize = 9;
+/// multipart 分隔符、字段头和 `bill_id` 等表单字段预留的字节数
+const FORM_OVERHEAD: u64 = 64 * 1024;
+
+pub fn init_attachment(config: AttachmentConfig) {
+    CONFIG
+        .set(config)
+        .unwrap_or_else(|_| panic!("attachment config already initialized"));
+}
+
+fn config() -> &'static AttachmentConfig {
+    CONFIG.get_or_init(AttachmentConfig::default)
+}
+
+/// 根据文件头判断类型，不信任客户端声明的 Content-Type 和扩展名。
+/// 只接受常见图片格式和 PDF。
+fn sniff_mime(head: &[u8]) -> Option<&'static str> {
+    match head {
+        [0xFF, 0xD8, 0xFF, ..] => Some("image/jpeg"),
+        [0x89, b'P', b'N', b'G', 0x0D, 0x0A, 0x1A, 0x0A, ..] => Some("image/png"),
+        [b'G', b'I', b'F', b'8', b'7' | b'9', b'a', ..] => Some("image/gif"),
+        [b'R', b'I', b'F', b'F', _, _, _, _, rest @ ..] if rest.starts_with(b"WEBP") => {
+            Some("image/webp")
+        }
+        // iPhone 拍照默认的 HEIC
+        [_, _, _, _, b'f', b't', b'y', b'p', brand @ ..]
+            if [b"heic", b"heix", b"mif1", b"msf1"]
+                .iter()
+                .any(|b| brand.starts_with(*b)) =>
+        {
+            Some("image/heic")
+        }
+        [b'%', b'P', b'D', b'F', b'-', ..] => Some("application/pdf"),
+        _ => None,
+    }
+}
+
+fn extension(mime: &str) -> &'static str {
+    match mime {
+        "image/jpeg" => "jpg",
+        "image/png" => "png",
+        "image/gif" => "gif",
+        "image/webp" => "webp",
+        "image/heic" => "heic",
+        "application/pdf" => "pdf",
+        _ => "bin",
+    }
+}
+
+/// 附件在磁盘上的路径，文件名取附件ID，不使用用户上传的文件名
+fn stored_path(attachment: &attachment_tb::Model) -> PathBuf {
+    PathBuf::from(&config().dir)
+        .join(attachment.user_id.to_string())
+        .join(format!("{}.{}", attachment.id, extension(&attachment.mime)))
+}
+
+/// 删除附件文件。数据库记录随账单级联删除，文件需要单独清理，失败时只记录日志。
+pub(crate) async fn remove_files(attachments: &[attachment_tb::Model]) {
+    for attachment in attachments {
+        let path = stored_path(attachment);
+        if let Err(e) = tokio::fs::remove_file(&path).await {
+            tracing::error!("删除附件文件 {} 失败：{e}", path.display());
+        }
+    }
+}
+
+/// 查询若干账单下的全部附件，删除账单前调用以便随后清理文件
+pub(crate) async fn bill_attachments(
+    db: &DatabaseConnection,
+    bill_ids: Vec<i32>,
+) -> Result<Vec<attachment_tb::Model>, DbErr> {
+    AttachmentTb::find()
+        .filter(attachment_tb::Column::BillId.is_in(bill_ids))
+        .all(db)
+        .await
+}
+
+/// 查找附件，权限跟随所属账单的账本，`write` 为 true 时要求 owner 或 editor
+async fn ledger_attachment(
+    db: &DatabaseConnection,
+    user_id: i32,
+    attachment_id: i32,
+    write: bool,
+) -> JsonResult<attachment_tb::Model> {
+    let attachment = AttachmentTb::find_by_id(attachment_id)
+        .one(db)
+        .await
+        .json_err()?
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的附件")))?;
+    ledger_bill(db, user_id, attachment.bill_id, write)
+        .await?
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的附件")))?;
+    Ok(attachment)
+}
+
+/// 上传账单附件，表单字段 `file` 可重复以一次上传多个文件
+#[handler]
+pub async fn add_attachment(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let max_size = config().max_size;
+    // 读取表单时 salvo 会先把整个请求体写入临时文件，须在此之前按 Content-Length 拦截
+    let limit = max_size * MAX_FILES as u64 + FORM_OVERHEAD;
+    match req
+        .headers()
+        .get(CONTENT_LENGTH)
+        .and_then(|v| v.to_str().ok())
+        .and_then(|v| v.parse::<u64>().ok())
+    {
+        Some(len) if len <= limit => {}
+        Some(_) => {
+            res_error(413, anyhow!("上传内容超过大小上限 {} KB", limit / 1024))?;
+            return Ok(());
+        }
+        None => {
+            res_error(411, anyhow!("上传附件须提供 Content-Length"))?;
+            return Ok(());
+        }
+    }
+    let bill_id = req
+        .form::<i32>("bill_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账单ID")))?;
+    let files = req
+        .files("file")
+        .await
+        .filter(|files| !files.is_empty())
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到上传文件")))?;
+    if files.len() > MAX_FILES {
+        res_error(400, anyhow!("一次最多上传 {MAX_FILES} 个文件"))?;
+        return Ok(());
+    }
+
+    let mut uploads = Vec::with_capacity(files.len());
+    for file in files {
+        let name = file.name().unwrap_or("attachment").to_owned();
+        let size = match i32::try_from(file.size()) {
+            Ok(size) if file.size() <= max_size => size,
+            _ => {
+                res_error(
+                    400,
+                    anyhow!("文件 {name} 超过大小上限 {} KB", max_size / 1024),
+                )?;
+                return Ok(());
+            }
+        };
+        let mut head = [0u8; 16];
+        let mut reader = tokio::fs::File::open(file.path())
+            .await
+            .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+        let len = reader
+            .read(&mut head)
+            .await
+            .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+        let Some(mime) = sniff_mime(&head[..len]) else {
+            res_error(400, anyhow!("文件 {name} 不是支持的图片或 PDF"))?;
+            return Ok(());
+        };
+        uploads.push((name, mime, size, file.path().clone()));
+    }
+
+    let db = orm::get_dao()?;
+    if ledger_bill(db, user_id, bill_id, true).await?.is_none() {
+        res_error(400, anyhow!("无效的账单"))?;
+        return Ok(());
+    }
+    let dir = PathBuf::from(&config().dir).join(user_id.to_string());
+    tokio::fs::create_dir_all(&dir)
+        .await
+        .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+
+    // 文件复制失败时回滚事务并清理已复制的文件
+    let txn = db.begin().await.json_err()?;
+    let now = Local::now().naive_local();
+    let mut saved = Vec::with_capacity(uploads.len());
+    for (name, mime, size, path) in uploads {
+        let mut info = attachment_tb::ActiveModel::new();
+        info.bill_id = Set(bill_id);
+        info.user_id = Set(user_id);
+        info.file_name = Set(name);
+        info.mime = Set(mime.to_owned());
+        info.size = Set(size);
+        info.created_time = Set(now);
+        let info = match info.insert(&txn).await {
+            Ok(info) => info,
+            Err(e) => {
+                remove_files(&saved).await;
+                return Err(JsonErr::from_error(500, anyhow!(e)));
+            }
+        };
+        if let Err(e) = tokio::fs::copy(&path, stored_path(&info)).await {
+            remove_files(&saved).await;
+            return Err(JsonErr::from_error(500, anyhow!(e)));
+        }
+        saved.push(info);
+    }
+    if let Err(e) = txn.commit().await {
+        remove_files(&saved).await;
+        return Err(JsonErr::from_error(500, anyhow!(e)));
+    }
+    let list = saved
+        .into_iter()
+        .map(|info| {
+            json!({
+                "id":info.id,
+                "file_name":info.file_name,
+                "mime":info.mime,
+                "size":info.size
+            })
+        })
+        .collect::<Vec<_>>();
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn attachment_list(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let bill_id = req
+        .query::<i32>("bill_id")
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账单ID")))?;
+    let db = orm::get_dao()?;
+    if ledger_bill(db, user_id, bill_id, false).await?.is_none() {
+        res_error(400, anyhow!("无效的账单"))?;
+        return Ok(());
+    }
+    let list = AttachmentTb::find()
+        .filter(attachment_tb::Column::BillId.eq(bill_id))
+        .order_by_asc(attachment_tb::Column::Id)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn download_attachment(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let attachment_id = req
+        .query::<i32>("id")
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的附件ID")))?;
+    let db = orm::get_dao()?;
+    let attachment = ledger_attachment(db, user_id, attachment_id, false).await?;
+    let mime = attachment
+        .mime
+        .parse::<Mime>()
+        .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+    let file = NamedFile::builder(stored_path(&attachment))
+        .attached_name(attachment.file_name.clone())
+        .content_type(mime)
+        .build()
+        .await
+        .map_err(|_e| JsonErr::from_error(404, anyhow!("附件文件不存在")))?;
+    file.send(req.headers(), res).await;
+    Ok(())
+}
+
+#[handler]
+pub async fn del_attachment(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let attachment_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的附件ID")))?;
+    let db = orm::get_dao()?;
+    let attachment = ledger_attachment(db, user_id, attachment_id, true).await?;
+    attachment
+        .clone()
+        .into_active_model()
+        .delete(db)
+        .await
+        .json_err()?;
+    remove_files(&[attachment]).await;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"删除成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sniff_mime_recognizes_supported_formats() {
+        let cases: [(&[u8], &str); 7] = [
+            (b"\xFF\xD8\xFF\xE0\x00\x10JFIF", "image/jpeg"),
+            (b"\x89PNG\r\n\x1A\n\x00\x00\x00\x0DIHDR", "image/png"),
+            (b"GIF89a\x01\x00", "image/gif"),
+            (b"GIF87a\x01\x00", "image/gif"),
+            (b"RIFF\x24\x00\x00\x00WEBPVP8 ", "image/webp"),
+            (b"\x00\x00\x00\x18ftypheic\x00\x00", "image/heic"),
+            (b"%PDF-1.7\n", "application/pdf"),
+        ];
+        for (head, mime) in cases {
+            assert_eq!(sniff_mime(head), Some(mime));
+        }
+    }
+
+    #[test]
+    fn sniff_mime_rejects_unknown_and_truncated_heads() {
+        let cases: [&[u8]; 6] = [
+            b"",
+            b"\xFF\xD8",
+            b"RIFF\x24\x00\x00\x00WAVE",
+            b"\x00\x00\x00\x18ftypmp42",
+            b"<html>",
+            b"PK\x03\x04",
+        ];
+        for head in cases {
+            assert_eq!(sniff_mime(head), None);
+        }
+    }
+}
@@ -1,18 +1,62 @@
-use crate::{auth::Authority, error::*, orm};
+use crate::attachment::{bill_attachments, remove_files};
+use crate::claim::match_reimbursement;
+use crate::ledger::{check_role, create_ledger, ledger_access, ledger_bill, member_role};
+use crate::rate::{base_currency, converted, converted_pay, ensure_currency, parse_currency};
+use crate::{auth::Authority, error::*, orm, password};
 use anyhow::anyhow;
 use chrono::{Local, NaiveDate};
 use rust_decimal::prelude::*;
 use salvo::prelude::*;
 use sea_orm::{
-    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait,
-    IntoActiveModel, PaginatorTrait, QueryFilter, Statement, Value,
+    ActiveModelBehavior, ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, DatabaseConnection, DatabaseTransaction, DbErr, EntityTrait, IntoActiveModel,
+    ItemsAndPagesNumber, Order, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, Select,
+    TransactionTrait,
+    sea_query::{Expr, Func, Query, SimpleExpr},
 };
+use serde::Deserialize;
 use serde_json::json;
+use std::collections::HashSet;
+use std::sync::OnceLock;
 
 use crate::error::IntoJsonError;
-use crate::orm::model::{prelude::*, *};
+use crate::orm::model::{prelude::*, sea_orm_active_enums::Direction, *};
 use rust_decimal::Decimal;
 
+pub(crate) fn parse_direction(direction: &str) -> JsonResult<Direction> {
+    match direction {
+        "expense" => Ok(Direction::Expense),
+        "income" => Ok(Direction::Income),
+        _ => Err(JsonErr::from_error(400, anyhow!("无效的收支方向"))),
+    }
+}
+
+/// 新用户注册时创建的默认标签，在 `config.toml` 的 `[[default_tags]]` 中配置
+#[derive(Deserialize)]
+pub struct DefaultTag {
+    name: String,
+    icon: Option<String>,
+    color: Option<String>,
+    #[serde(default)]
+    children: Vec<String>,
+}
+
+static DEFAULT_TAGS: OnceLock<Vec<DefaultTag>> = OnceLock::new();
+
+pub fn init_default_tags(tags: Vec<DefaultTag>) {
+    let mut names = HashSet::new();
+    for name in tags
+        .iter()
+        .flat_map(|tag| std::iter::once(&tag.name).chain(&tag.children))
+    {
+        assert!(names.insert(name), "默认标签重复：{name}");
+    }
+    DEFAULT_TAGS
+        .set(tags)
+        .unwrap_or_else(|_| panic!("default tags already initialized"));
+}
+
 #[handler]
 pub async fn registry(req: &mut Request, res: &mut Response) -> JsonResult<()> {
     let account = req
@@ -40,14 +84,47 @@ pub async fn registry(req: &mut Request, res: &mut Response) -> JsonResult<()> {
         res_error(400, anyhow!("账号已存在"))?;
         return Ok(());
     }
-    let salt_pass = format!("{:x}", md5::compute(pass));
+    let hashed_pass = password::hash(&pass).map_err(|e| JsonErr::from_error(500, e))?;
     let mut user = user_tb::ActiveModel::new();
-    user.pass = Set(salt_pass);
+    user.pass = Set(hashed_pass);
     user.account = Set(account);
     let now = Local::now().naive_local();
     user.created_time = Set(now);
     user.updated_time = Set(now);
-    user.insert(db).await.json_err()?;
+    // 用户、个人账本与默认标签在同一个事务中写入，避免注册成功却没有任何标签
+    let txn = db.begin().await.json_err()?;
+    let user = user.insert(&txn).await.json_err()?;
+    let ledger = create_ledger(&txn, user.id, "个人账本".to_owned(), true)
+        .await
+        .json_err()?;
+    for (position, tag) in DEFAULT_TAGS.get().into_iter().flatten().enumerate() {
+        let mut info = tag_tb::ActiveModel::new();
+        info.name = Set(tag.name.clone());
+        info.user_id = Set(user.id);
+        info.ledger_id = Set(ledger.id);
+        info.icon = Set(tag.icon.clone());
+        info.color = Set(tag.color.clone());
+        info.position = Set(position as i32);
+        info.created_time = Set(now);
+        info.updated_time = Set(now);
+        let parent = info.insert(&txn).await.json_err()?;
+        if tag.children.is_empty() {
+            continue;
+        }
+        let children = tag.children.iter().enumerate().map(|(position, name)| {
+            let mut info = tag_tb::ActiveModel::new();
+            info.name = Set(name.clone());
+            info.user_id = Set(user.id);
+            info.ledger_id = Set(ledger.id);
+            info.parent_id = Set(Some(parent.id));
+            info.position = Set(position as i32);
+            info.created_time = Set(now);
+            info.updated_time = Set(now);
+            info
+        });
+        TagTb::insert_many(children).exec(&txn).await.json_err()?;
+    }
+    txn.commit().await.json_err()?;
     res.render(Text::Json(
         json!({
             "status":"success",
@@ -69,15 +146,21 @@ pub async fn login(req: &mut Request, res: &mut Response, depot: &mut Depot) ->
         .await
         .ok_or(JsonErr::from_error(400, anyhow!("password is required")))?;
 
-    let salt_pass = format!("{:x}", md5::compute(pass));
     let db = orm::get_dao()?;
     let info = UserTb::find()
         .filter(user_tb::Column::Account.eq(account))
-        .filter(user_tb::Column::Pass.eq(salt_pass))
         .one(db)
         .await
         .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?
+        .filter(|info| password::verify(&pass, &info.pass))
         .ok_or(JsonErr::from_error(400, anyhow!("账户或密码错误")))?;
+    if password::is_legacy(&info.pass) {
+        let hashed_pass = password::hash(&pass).map_err(|e| JsonErr::from_error(500, e))?;
+        let mut user = info.clone().into_active_model();
+        user.pass = Set(hashed_pass);
+        user.updated_time = Set(Local::now().naive_local());
+        user.update(db).await.json_err()?;
+    }
     let authority = depot
         .obtain::<Authority>()
         .map_err(|e| JsonErr::from_error(500, anyhow!("签名错误 {e:?}")))?;
@@ -95,11 +178,8 @@ pub async fn login(req: &mut Request, res: &mut Response, depot: &mut Depot) ->
     Ok(())
 }
 
-#[handler]
-pub async fn bill_list(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
-    let user_id = *depot
-        .get::<i32>("user_id")
-        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+/// 按请求参数构造账单筛选条件，`bill/list` 等接口共用。`ledger_id` 须已校验过访问权限
+pub(crate) fn bill_filter(req: &Request, ledger_id: i32) -> JsonResult<Select<BillTb>> {
     let start_date = req
         .query::<String>("begin")
         .ok_or(JsonErr::from_error(400, anyhow!("未获取到起始日期")))?;
@@ -112,60 +192,235 @@ pub async fn bill_list(req: &mut Request, res: &mut Response, depot: &mut Depot)
     let end = NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
         .map_err(|e| JsonErr::from_error(400, anyhow!("结束日期解析错误：{}", e)))?;
 
-    let delta_time = end.signed_duration_since(begin);
+    if end < begin {
+        return Err(JsonErr::from_error(400, anyhow!("无效的日期范围")));
+    }
 
-    if delta_time.num_seconds() < 0 {
-        res_error(400, anyhow!("无效的日期范围"))?;
-        return Ok(());
+    let mut select = BillTb::find()
+        .filter(bill_tb::Column::LedgerId.eq(ledger_id))
+        .filter(bill_tb::Column::TransactionDate.between(begin, end));
+
+    if let Some(tag_ids) = tag_ids_param(req)? {
+        select = select.filter(tag_with_children(tag_ids));
+    }
+    if let Some(account_ids) = req.query::<String>("account_ids").filter(|s| !s.is_empty()) {
+        let account_ids = account_ids
+            .split(',')
+            .map(|id| id.trim().parse::<i32>())
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(|e| JsonErr::from_error(400, anyhow!("无效的账户ID：{e}")))?;
+        select = select.filter(bill_tb::Column::AccountId.is_in(account_ids));
+    }
+    if let Some(direction) = req.query::<String>("direction").filter(|s| !s.is_empty()) {
+        select = select.filter(bill_tb::Column::Direction.eq(parse_direction(&direction)?));
+    }
+    if let Some(min_pay) = req.query::<String>("min_pay").filter(|s| !s.is_empty()) {
+        let min_pay = Decimal::from_str(&min_pay)
+            .map_err(|e| JsonErr::from_error(400, anyhow!("无效的最小金额 {e}")))?;
+        select = select.filter(bill_tb::Column::Pay.gte(min_pay));
     }
+    if let Some(max_pay) = req.query::<String>("max_pay").filter(|s| !s.is_empty()) {
+        let max_pay = Decimal::from_str(&max_pay)
+            .map_err(|e| JsonErr::from_error(400, anyhow!("无效的最大金额 {e}")))?;
+        select = select.filter(bill_tb::Column::Pay.lte(max_pay));
+    }
+    if let Some(keyword) = req.query::<String>("keyword").filter(|s| !s.is_empty()) {
+        select = select.filter(bill_tb::Column::Comment.contains(keyword));
+    }
+    Ok(select)
+}
 
-    let sql = "SELECT
-	bill_tb.*,
-	tag_tb.`name` AS tagName 
-FROM
-	bill_tb
-	LEFT JOIN tag_tb ON tag_tb.id = bill_tb.tag_id 
-WHERE
-	bill_tb.user_id = ?
-	AND bill_tb.transaction_date <= ? AND bill_tb.transaction_date >= ?";
-    let db = orm::get_dao()?;
+/// 查询参数 `tag_ids`，逗号分隔
+pub(crate) fn tag_ids_param(req: &Request) -> JsonResult<Option<Vec<i32>>> {
+    let Some(tag_ids) = req.query::<String>("tag_ids").filter(|s| !s.is_empty()) else {
+        return Ok(None);
+    };
+    tag_ids
+        .split(',')
+        .map(|id| id.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map(Some)
+        .map_err(|e| JsonErr::from_error(400, anyhow!("无效的标签ID：{e}")))
+}
 
-    let result = BillTb::find()
-        .from_raw_sql(Statement::from_sql_and_values(
-            sea_orm::DatabaseBackend::MySql,
-            sql,
-            [
-                Value::Int(Some(user_id)),
-                Value::ChronoDate(Some(Box::new(end))),
-                Value::ChronoDate(Some(Box::new(begin))),
-            ],
+/// `tag` 属于给定标签或其子标签，筛选上级标签时包含子标签
+fn tag_in(tag: SimpleExpr, tag_ids: Vec<i32>) -> SimpleExpr {
+    Expr::expr(tag.clone())
+        .is_in(tag_ids.clone())
+        .or(Expr::expr(tag).in_subquery(
+            Query::select()
+                .column(tag_tb::Column::Id)
+                .from(TagTb)
+                .and_where(tag_tb::Column::ParentId.is_in(tag_ids))
+                .to_owned(),
         ))
-        .into_json()
-        .all(db)
+}
+
+/// 账单本身或其任一拆分明细属于给定标签
+pub(crate) fn tag_with_children(tag_ids: Vec<i32>) -> SimpleExpr {
+    tag_in(
+        Expr::col((BillTb, bill_tb::Column::TagId)).into(),
+        tag_ids.clone(),
+    )
+    .or(bill_tb::Column::Id.in_subquery(
+        Query::select()
+            .column(bill_split_tb::Column::BillId)
+            .from(BillSplitTb)
+            .and_where(tag_in(
+                Expr::col((BillSplitTb, bill_split_tb::Column::TagId)).into(),
+                tag_ids,
+            ))
+            .to_owned(),
+    ))
+}
+
+/// 连接拆分明细后每行的标签与金额：有明细取明细，否则取账单本身
+pub(crate) const LINE_TAG: &str = "COALESCE(`bill_split_tb`.`tag_id`, `bill_tb`.`tag_id`)";
+const LINE_AMOUNT: &str = "COALESCE(`bill_split_tb`.`amount`, `bill_tb`.`pay`)";
+
+/// 按拆分明细展开账单，未拆分的账单保持一行。统计、预算按明细的标签归类，
+/// 配合 `line_tag`、`line_tag_in`、`line_sum` 使用。
+pub(crate) fn split_lines(select: Select<BillTb>) -> Select<BillTb> {
+    select.left_join(BillSplitTb)
+}
+
+pub(crate) fn line_tag() -> SimpleExpr {
+    Expr::cust(LINE_TAG)
+}
+
+pub(crate) fn line_tag_in(tag_ids: Vec<i32>) -> SimpleExpr {
+    tag_in(line_tag(), tag_ids)
+}
+
+/// 按明细金额汇总，转账不计入
+pub(crate) fn line_sum(direction: Direction, viewer: i32) -> SimpleExpr {
+    pay_sum_when(
+        bill_tb::Column::Direction
+            .eq(direction)
+            .and(bill_tb::Column::TransferId.is_null()),
+        converted(LINE_AMOUNT, viewer),
+    )
+}
+
+fn pay_sum_when(condition: SimpleExpr, amount: SimpleExpr) -> SimpleExpr {
+    Func::sum(Expr::case(condition, amount).finally(0)).into()
+}
+
+/// 按收支方向汇总金额的 SQL 表达式，账户间转账不算收支，不计入
+pub(crate) fn direction_sum(direction: Direction, viewer: i32) -> SimpleExpr {
+    pay_sum_when(
+        bill_tb::Column::Direction
+            .eq(direction)
+            .and(bill_tb::Column::TransferId.is_null()),
+        converted_pay(viewer),
+    )
+}
+
+/// 个人收支汇总，在 `direction_sum` 的基础上去掉已报销的垫付支出和对应的报销收入
+pub(crate) fn spending_sum(direction: Direction, viewer: i32) -> SimpleExpr {
+    let reimbursement = match direction {
+        Direction::Expense => bill_tb::Column::ReimbursedBy.is_null(),
+        Direction::Income => Expr::cust(
+            "NOT EXISTS (SELECT 1 FROM `bill_tb` `r` WHERE `r`.`reimbursed_by` = `bill_tb`.`id`)",
+        ),
+    };
+    pay_sum_when(
+        bill_tb::Column::Direction
+            .eq(direction)
+            .and(bill_tb::Column::TransferId.is_null())
+            .and(reimbursement),
+        converted_pay(viewer),
+    )
+}
+
+/// 按资金流向汇总金额，包含转账，用于计算账户余额
+pub(crate) fn flow_sum(direction: Direction, viewer: i32) -> SimpleExpr {
+    pay_sum_when(
+        bill_tb::Column::Direction.eq(direction),
+        converted_pay(viewer),
+    )
+}
+
+/// 排序字段与方向，默认按交易日期倒序
+pub(crate) fn bill_order(req: &Request, select: Select<BillTb>) -> JsonResult<Select<BillTb>> {
+    let column = match req.query::<String>("sort").as_deref() {
+        None | Some("transaction_date") => bill_tb::Column::TransactionDate,
+        Some("pay") => bill_tb::Column::Pay,
+        Some("created_time") => bill_tb::Column::CreatedTime,
+        Some(_) => return Err(JsonErr::from_error(400, anyhow!("无效的排序字段"))),
+    };
+    let order = match req.query::<String>("order").as_deref() {
+        None | Some("desc") => Order::Desc,
+        Some("asc") => Order::Asc,
+        Some(_) => return Err(JsonErr::from_error(400, anyhow!("无效的排序方向"))),
+    };
+    Ok(select
+        .order_by(column, order.clone())
+        .order_by(bill_tb::Column::Id, order))
+}
+
+#[handler]
+pub async fn bill_list(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let page = req.query::<u64>("page").unwrap_or(1).max(1);
+    let page_size = req.query::<u64>("page_size").unwrap_or(20).clamp(1, 100);
+    let db = orm::get_dao()?;
+    let ledger_id = ledger_access(db, user_id, req.query::<i32>("ledger_id"), false).await?;
+    let select = bill_filter(req, ledger_id)?;
+
+    // 已报销的垫付支出及其报销收入不计入收支合计
+    let (income_amount, expense_amount, reimbursed_amount) = select
+        .clone()
+        .select_only()
+        .column_as(spending_sum(Direction::Income, user_id), "income_amount")
+        .column_as(spending_sum(Direction::Expense, user_id), "expense_amount")
+        .column_as(
+            pay_sum_when(
+                bill_tb::Column::Direction
+                    .eq(Direction::Expense)
+                    .and(bill_tb::Column::TransferId.is_null())
+                    .and(bill_tb::Column::ReimbursedBy.is_not_null()),
+                converted_pay(user_id),
+            ),
+            "reimbursed_amount",
+        )
+        .into_tuple::<(Option<Decimal>, Option<Decimal>, Option<Decimal>)>()
+        .one(db)
         .await
-        .map_err(|e| JsonErr::from_error(400, anyhow!(e)))?;
-
-    let mut pay_amount = rust_decimal::Decimal::new(0, 2);
-    for bill in &result {
-        _ = bill.as_object().inspect(|v| {
-            v.get("pay").inspect(|v| {
-                v.as_str().inspect(|v| {
-                    if let Ok(v) = Decimal::from_str(v) {
-                        pay_amount += v;
-                    }
-                });
-            });
-        });
-        // if let Some(v) = bill.as_object() {
-        //     if let Some(v) = v.get("pay") {
-        //         if let Some(v) = v.as_str() {
-        //             if let Ok(v) = Decimal::from_str(v) {
-        //                 pay_amount += v;
-        //             }
-        //         }
-        //     }
-        // }
-    }
+        .json_err()?
+        .unwrap_or_default();
+    let income_amount = income_amount.unwrap_or_default();
+    let expense_amount = expense_amount.unwrap_or_default();
+    let reimbursed_amount = reimbursed_amount.unwrap_or_default();
+
+    let paginator = bill_order(req, select)?
+        .column_as(converted_pay(user_id), "basePay")
+        .column_as(
+            Expr::cust(
+                "(SELECT COUNT(*) FROM `bill_split_tb` WHERE `bill_split_tb`.`bill_id` = `bill_tb`.`id`)",
+            ),
+            "splitCount",
+        )
+        .column_as(
+            Expr::cust(
+                "(SELECT COUNT(*) FROM `bill_share_tb` WHERE `bill_share_tb`.`bill_id` = `bill_tb`.`id`)",
+            ),
+            "shareCount",
+        )
+        .left_join(TagTb)
+        .column_as(tag_tb::Column::Name, "tagName")
+        .left_join(AccountTb)
+        .column_as(account_tb::Column::Name, "accountName")
+        .into_json()
+        .paginate(db, page_size);
+    let ItemsAndPagesNumber {
+        number_of_items,
+        number_of_pages,
+    } = paginator.num_items_and_pages().await.json_err()?;
+    let result = paginator.fetch_page(page - 1).await.json_err()?;
+
     res.render(Text::Json(
         json!({
             "status":"success",
@@ -173,7 +428,16 @@ WHERE
             "msg":{
                 "data":{
                     "list":result,
-                    "pay_amount":pay_amount
+                    "page":page,
+                    "page_size":page_size,
+                    "total":number_of_items,
+                    "pages":number_of_pages,
+                    "base_currency":base_currency(db, user_id).await.json_err()?,
+                    "pay_amount":expense_amount,
+                    "income_amount":income_amount,
+                    "expense_amount":expense_amount,
+                    "reimbursed_amount":reimbursed_amount,
+                    "net_amount":income_amount - expense_amount
                 }
             }
         })
@@ -193,10 +457,10 @@ pub async fn bill_add(req: &mut Request, res: &mut Response, depot: &mut Depot)
         .ok_or(JsonErr::from_error(400, anyhow!("未获取到支出金额")))?;
     let pay = Decimal::from_str(&pay)
         .map_err(|e| JsonErr::from_error(400, anyhow!("无效的支出金额 {e}")))?;
-    let pay_method = req
-        .form::<String>("pay_method")
+    let account_id = req
+        .form::<i32>("account_id")
         .await
-        .ok_or(JsonErr::from_error(400, anyhow!("未获取到支付方式")))?;
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到支付账户")))?;
     let comment = req
         .form::<String>("comment")
         .await
@@ -214,10 +478,32 @@ pub async fn bill_add(req: &mut Request, res: &mut Response, depot: &mut Depot)
         .await
         .ok_or(JsonErr::from_error(400, anyhow!("未获取到交易标签")))?;
 
+    let direction = match req.form::<String>("direction").await {
+        Some(direction) => parse_direction(&direction)?,
+        None => Direction::Expense,
+    };
+    let reimbursable = req
+        .form::<i8>("reimbursable")
+        .await
+        .map_or(0, |v| (v > 0) as i8);
+    if reimbursable == 1 && direction != Direction::Expense {
+        res_error(400, anyhow!("只有支出可以报销"))?;
+        return Ok(());
+    }
+
     let db = orm::get_dao()?;
+    let ledger_id = ledger_access(db, user_id, req.form::<i32>("ledger_id").await, true).await?;
+    let currency = match req.form::<String>("currency").await {
+        Some(currency) => {
+            let currency = parse_currency(&currency)?;
+            ensure_currency(db, &currency).await?;
+            currency
+        }
+        None => base_currency(db, user_id).await.json_err()?,
+    };
     if TagTb::find()
         .filter(tag_tb::Column::Id.eq(tag_id))
-        .filter(tag_tb::Column::UserId.eq(user_id))
+        .filter(tag_tb::Column::LedgerId.eq(ledger_id))
         .one(db)
         .await
         .json_err()?
@@ -226,18 +512,35 @@ pub async fn bill_add(req: &mut Request, res: &mut Response, depot: &mut Depot)
         res_error(400, anyhow!("无效的标签"))?;
         return Ok(());
     }
+    // 共享账本中记账使用记账人自己的账户
+    if AccountTb::find()
+        .filter(account_tb::Column::Id.eq(account_id))
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+        .is_none()
+    {
+        res_error(400, anyhow!("无效的账户"))?;
+        return Ok(());
+    }
 
     let mut info = bill_tb::ActiveModel::new();
     info.comment = Set(Some(comment));
     info.pay = Set(Some(pay));
-    info.pay_method = Set(pay_method);
+    info.account_id = Set(account_id);
     info.transaction_date = Set(transaction_date);
     info.user_id = Set(user_id);
-    info.tag_id = Set(tag_id);
+    info.ledger_id = Set(ledger_id);
+    info.tag_id = Set(Some(tag_id));
+    info.direction = Set(direction);
+    info.currency = Set(currency);
+    info.reimbursable = Set(reimbursable);
     let now = Local::now().naive_local();
     info.created_time = Set(now);
     info.updated_time = Set(now);
-    info.insert(db).await.json_err()?;
+    let info = info.insert(db).await.json_err()?;
+    match_reimbursement(db, &info).await.json_err()?;
     res.render(Text::Json(
         json!({
             "status":"success",
@@ -259,15 +562,15 @@ pub async fn del_bill(req: &mut Request, res: &mut Response, depot: &mut Depot)
         .await
         .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账单ID")))?;
     let db = orm::get_dao()?;
-    if let Some(info) = BillTb::find()
-        .filter(bill_tb::Column::Id.eq(bill_id))
-        .filter(bill_tb::Column::UserId.eq(user_id))
-        .one(db)
-        .await
-        .json_err()?
-    {
+    if let Some(info) = ledger_bill(db, user_id, bill_id, true).await? {
+        if info.transfer_id.is_some() {
+            res_error(400, anyhow!("转账账单请通过转账删除"))?;
+            return Ok(());
+        }
+        let attachments = bill_attachments(db, vec![bill_id]).await.json_err()?;
         let info = info.into_active_model();
         info.delete(db).await.json_err()?;
+        remove_files(&attachments).await;
         res.render(Text::Json(
             json!({
                 "status":"success",
@@ -288,12 +591,26 @@ pub async fn tag_list(req: &mut Request, res: &mut Response, depot: &mut Depot)
         .get::<i32>("user_id")
         .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
     let db = orm::get_dao()?;
-    let list = TagTb::find()
-        .filter(tag_tb::Column::UserId.eq(user_id))
+    let ledger_id = ledger_access(db, user_id, req.form::<i32>("ledger_id").await, false).await?;
+    let tags = TagTb::find()
+        .filter(tag_tb::Column::LedgerId.eq(ledger_id))
+        .order_by_asc(tag_tb::Column::Position)
+        .order_by_asc(tag_tb::Column::Id)
         .into_json()
         .all(db)
         .await
         .json_err()?;
+    // 按两级树形返回，子标签放在上级标签的 children 中
+    let (children, mut list): (Vec<_>, Vec<_>) = tags
+        .into_iter()
+        .partition(|tag| !tag["parent_id"].is_null());
+    for tag in &mut list {
+        tag["children"] = children
+            .iter()
+            .filter(|child| child["parent_id"] == tag["id"])
+            .cloned()
+            .collect();
+    }
     res.render(Text::Json(
         json!({
             "status":"success",
@@ -309,6 +626,35 @@ pub async fn tag_list(req: &mut Request, res: &mut Response, depot: &mut Depot)
     Ok(())
 }
 
+/// 标签颜色统一为 `#RRGGBB` 大写形式
+fn parse_color(color: &str) -> JsonResult<String> {
+    match color.strip_prefix('#') {
+        Some(hex) if hex.len() == 6 && hex.chars().all(|c| c.is_ascii_hexdigit()) => {
+            Ok(color.to_ascii_uppercase())
+        }
+        _ => Err(JsonErr::from_error(
+            400,
+            anyhow!("无效的颜色，须为 #RRGGBB"),
+        )),
+    }
+}
+
+/// 校验上级标签属于同一账本且本身是一级标签
+async fn parent_tag(
+    db: &DatabaseConnection,
+    ledger_id: i32,
+    parent_id: i32,
+) -> JsonResult<tag_tb::Model> {
+    TagTb::find()
+        .filter(tag_tb::Column::Id.eq(parent_id))
+        .filter(tag_tb::Column::LedgerId.eq(ledger_id))
+        .filter(tag_tb::Column::ParentId.is_null())
+        .one(db)
+        .await
+        .json_err()?
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的上级标签")))
+}
+
 #[handler]
 pub async fn add_tag(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
     let user_id = *depot
@@ -319,8 +665,18 @@ pub async fn add_tag(req: &mut Request, res: &mut Response, depot: &mut Depot) -
         .await
         .ok_or(JsonErr::from_error(400, anyhow!("未获取到有效标签")))?;
     let db = orm::get_dao()?;
+    let ledger_id = ledger_access(db, user_id, req.form::<i32>("ledger_id").await, true).await?;
+    let parent_id = match req.form::<i32>("parent_id").await {
+        Some(parent_id) => Some(parent_tag(db, ledger_id, parent_id).await?.id),
+        None => None,
+    };
+    let icon = req.form::<String>("icon").await.filter(|s| !s.is_empty());
+    let color = match req.form::<String>("color").await {
+        Some(color) => Some(parse_color(&color)?),
+        None => None,
+    };
     if TagTb::find()
-        .filter(tag_tb::Column::UserId.eq(user_id))
+        .filter(tag_tb::Column::LedgerId.eq(ledger_id))
         .filter(tag_tb::Column::Name.eq(&name))
         .count(db)
         .await
@@ -333,6 +689,25 @@ pub async fn add_tag(req: &mut Request, res: &mut Response, depot: &mut Depot) -
     let mut info = tag_tb::ActiveModel::new();
     info.name = Set(name);
     info.user_id = Set(user_id);
+    info.ledger_id = Set(ledger_id);
+    info.parent_id = Set(parent_id);
+    info.icon = Set(icon);
+    info.color = Set(color);
+    // 新标签排在同级标签的最后
+    let position = TagTb::find()
+        .select_only()
+        .column_as(tag_tb::Column::Position.max(), "position")
+        .filter(tag_tb::Column::LedgerId.eq(ledger_id))
+        .filter(match parent_id {
+            Some(parent_id) => tag_tb::Column::ParentId.eq(parent_id),
+            None => tag_tb::Column::ParentId.is_null(),
+        })
+        .into_tuple::<Option<i32>>()
+        .one(db)
+        .await
+        .json_err()?
+        .flatten();
+    info.position = Set(position.map_or(0, |p| p + 1));
     let now = Local::now().naive_local();
     info.created_time = Set(now);
     info.updated_time = Set(now);
@@ -348,35 +723,510 @@ pub async fn add_tag(req: &mut Request, res: &mut Response, depot: &mut Depot) -
     Ok(())
 }
 
+/// 将 `from` 标签下的账单、拆分明细、周期账单和子标签转移到 `to`，调用方随后删除 `from`。
+/// `to` 为二级标签（且不是 `from` 的子标签）时，子标签改为一级标签。
+async fn reassign_tag(
+    txn: &DatabaseTransaction,
+    from: &tag_tb::Model,
+    to: &tag_tb::Model,
+) -> Result<(), DbErr> {
+    BillTb::update_many()
+        .col_expr(bill_tb::Column::TagId, Expr::value(to.id))
+        .filter(bill_tb::Column::TagId.eq(from.id))
+        .exec(txn)
+        .await?;
+    RecurringTb::update_many()
+        .col_expr(recurring_tb::Column::TagId, Expr::value(to.id))
+        .filter(recurring_tb::Column::TagId.eq(from.id))
+        .exec(txn)
+        .await?;
+    BillSplitTb::update_many()
+        .col_expr(bill_split_tb::Column::TagId, Expr::value(to.id))
+        .filter(bill_split_tb::Column::TagId.eq(from.id))
+        .exec(txn)
+        .await?;
+    let new_parent = match to.parent_id {
+        None => Some(to.id),
+        Some(parent_id) if parent_id == from.id => {
+            TagTb::update_many()
+                .col_expr(tag_tb::Column::ParentId, Expr::value(None::<i32>))
+                .filter(tag_tb::Column::Id.eq(to.id))
+                .exec(txn)
+                .await?;
+            Some(to.id)
+        }
+        Some(_) => None,
+    };
+    TagTb::update_many()
+        .col_expr(tag_tb::Column::ParentId, Expr::value(new_parent))
+        .filter(tag_tb::Column::ParentId.eq(from.id))
+        .filter(tag_tb::Column::Id.ne(to.id))
+        .exec(txn)
+        .await?;
+    Ok(())
+}
+
+/// 查找当前用户可以修改的标签，须为标签所在账本的 owner 或 editor
+async fn ledger_tag(
+    db: &DatabaseConnection,
+    user_id: i32,
+    tag_id: i32,
+) -> JsonResult<Option<tag_tb::Model>> {
+    let Some(tag) = TagTb::find_by_id(tag_id).one(db).await.json_err()? else {
+        return Ok(None);
+    };
+    let role = member_role(db, user_id, tag.ledger_id).await.json_err()?;
+    Ok(check_role(role, true)?.then_some(tag))
+}
+
+/// 删除标签。仍有账单引用时须通过 `target_id` 指定转移到的标签，
+/// 该标签的预算随之删除
 #[handler]
 pub async fn del_tag(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
     let user_id = *depot
         .get::<i32>("user_id")
         .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
     let tag_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的标签ID")))?;
+    let db = orm::get_dao()?;
+    let Some(info) = ledger_tag(db, user_id, tag_id).await? else {
+        res_error(400, anyhow!("无效的标签"))?;
+        return Ok(());
+    };
+    let target = match req.form::<i32>("target_id").await {
+        Some(target_id) if target_id == tag_id => {
+            res_error(400, anyhow!("目标标签不能是自身"))?;
+            return Ok(());
+        }
+        Some(target_id) => match ledger_tag(db, user_id, target_id).await? {
+            Some(target) if target.ledger_id == info.ledger_id => Some(target),
+            _ => {
+                res_error(400, anyhow!("无效的目标标签"))?;
+                return Ok(());
+            }
+        },
+        None => None,
+    };
+    let bill_count = BillTb::find()
+        .filter(bill_tb::Column::TagId.eq(tag_id))
+        .count(db)
+        .await
+        .json_err()?;
+    let recurring_count = RecurringTb::find()
+        .filter(recurring_tb::Column::TagId.eq(tag_id))
+        .count(db)
+        .await
+        .json_err()?;
+    let split_count = BillSplitTb::find()
+        .filter(bill_split_tb::Column::TagId.eq(tag_id))
+        .count(db)
+        .await
+        .json_err()?;
+    if target.is_none() && bill_count + recurring_count + split_count != 0 {
+        res_error(
+            400,
+            anyhow!(
+                "该标签仍有 {bill_count} 条账单、{split_count} 条拆分明细、{recurring_count} 条周期账单，请指定转移的目标标签"
+            ),
+        )?;
+        return Ok(());
+    }
+    let txn = db.begin().await.json_err()?;
+    if let Some(target) = &target {
+        reassign_tag(&txn, &info, target).await.json_err()?;
+    }
+    info.into_active_model().delete(&txn).await.json_err()?;
+    txn.commit().await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "bill_count":bill_count,
+                    "recurring_count":recurring_count,
+                    "split_count":split_count,
+                    "target_id":target.map(|t| t.id)
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 将 `source_id` 标签合并到 `target_id`，在同一个事务中转移引用并删除源标签，
+/// 源标签的预算随之删除
+#[handler]
+pub async fn merge_tag(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let source_id = req
+        .form::<i32>("source_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的源标签ID")))?;
+    let target_id = req
+        .form::<i32>("target_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的目标标签ID")))?;
+    if source_id == target_id {
+        res_error(400, anyhow!("源标签与目标标签不能相同"))?;
+        return Ok(());
+    }
+    let db = orm::get_dao()?;
+    let (Some(source), Some(target)) = (
+        ledger_tag(db, user_id, source_id).await?,
+        ledger_tag(db, user_id, target_id).await?,
+    ) else {
+        res_error(400, anyhow!("无效的标签"))?;
+        return Ok(());
+    };
+    if source.ledger_id != target.ledger_id {
+        res_error(400, anyhow!("只能合并同一账本中的标签"))?;
+        return Ok(());
+    }
+    let txn = db.begin().await.json_err()?;
+    reassign_tag(&txn, &source, &target).await.json_err()?;
+    source.into_active_model().delete(&txn).await.json_err()?;
+    txn.commit().await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"合并成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn update_bill(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let bill_id = req
         .form::<i32>("id")
         .await
         .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账单ID")))?;
     let db = orm::get_dao()?;
-    if let Some(info) = TagTb::find()
-        .filter(tag_tb::Column::Id.eq(tag_id))
-        .filter(tag_tb::Column::UserId.eq(user_id))
-        .one(db)
+    let Some(info) = ledger_bill(db, user_id, bill_id, true).await? else {
+        res_error(400, anyhow!("无效的账单"))?;
+        return Ok(());
+    };
+    if info.transfer_id.is_some() {
+        res_error(400, anyhow!("转账账单不能直接修改"))?;
+        return Ok(());
+    }
+    let (ledger_id, recorder_id) = (info.ledger_id, info.user_id);
+    let (claim_id, reimbursed) = (info.claim_id, info.reimbursed_by.is_some());
+    let shared = BillShareTb::find()
+        .filter(bill_share_tb::Column::BillId.eq(bill_id))
+        .count(db)
+        .await
+        .json_err()?
+        != 0;
+    // 还款记录按账单金额、方向与币种冲抵欠款
+    let repaid = DebtRepaymentTb::find()
+        .filter(debt_repayment_tb::Column::BillId.eq(bill_id))
+        .count(db)
+        .await
+        .json_err()?
+        != 0;
+    // 收入已用于冲抵报销支出
+    let reimbursing = BillTb::find()
+        .filter(bill_tb::Column::ReimbursedBy.eq(bill_id))
+        .count(db)
+        .await
+        .json_err()?
+        != 0;
+    let mut info = info.into_active_model();
+    if let Some(pay) = req.form::<String>("pay").await {
+        let pay = Decimal::from_str(&pay)
+            .map_err(|e| JsonErr::from_error(400, anyhow!("无效的支出金额 {e}")))?;
+        // 拆分明细之和须等于账单金额
+        if info.pay.as_ref() != &Some(pay)
+            && BillSplitTb::find()
+                .filter(bill_split_tb::Column::BillId.eq(bill_id))
+                .count(db)
+                .await
+                .json_err()?
+                != 0
+        {
+            res_error(400, anyhow!("账单已拆分，请先修改拆分明细"))?;
+            return Ok(());
+        }
+        if shared && info.pay.as_ref() != &Some(pay) {
+            res_error(400, anyhow!("账单已分摊，请先修改分摊成员"))?;
+            return Ok(());
+        }
+        if reimbursed && info.pay.as_ref() != &Some(pay) {
+            res_error(400, anyhow!("账单已报销，请先撤销报销"))?;
+            return Ok(());
+        }
+        if reimbursing && info.pay.as_ref() != &Some(pay) {
+            res_error(400, anyhow!("账单已用于报销，请先撤销报销"))?;
+            return Ok(());
+        }
+        if repaid && info.pay.as_ref() != &Some(pay) {
+            res_error(400, anyhow!("账单已关联还款，请先删除还款"))?;
+            return Ok(());
+        }
+        info.pay = Set(Some(pay));
+    }
+    if let Some(comment) = req.form::<String>("comment").await {
+        info.comment = Set(Some(comment));
+    }
+    if let Some(transaction_date) = req.form::<String>("transaction_date").await {
+        let transaction_date = NaiveDate::parse_from_str(&transaction_date, "%Y-%m-%d")
+            .map_err(|e| JsonErr::from_error(400, anyhow!("交易日期解析错误：{}", e)))?;
+        // 同一周期规则每天只生成一笔账单，(recurring_id, transaction_date) 唯一
+        if let Some(recurring_id) = *info.recurring_id.as_ref()
+            && BillTb::find()
+                .filter(bill_tb::Column::RecurringId.eq(recurring_id))
+                .filter(bill_tb::Column::TransactionDate.eq(transaction_date))
+                .filter(bill_tb::Column::Id.ne(bill_id))
+                .count(db)
+                .await
+                .json_err()?
+                != 0
+        {
+            res_error(400, anyhow!("该周期规则在 {transaction_date} 已有账单"))?;
+            return Ok(());
+        }
+        info.transaction_date = Set(transaction_date);
+    }
+    if let Some(direction) = req.form::<String>("direction").await {
+        let direction = parse_direction(&direction)?;
+        if shared && direction != Direction::Expense {
+            res_error(400, anyhow!("账单已分摊，不能改为收入"))?;
+            return Ok(());
+        }
+        if reimbursing && info.direction.as_ref() != &direction {
+            res_error(400, anyhow!("账单已用于报销，请先撤销报销"))?;
+            return Ok(());
+        }
+        if repaid && info.direction.as_ref() != &direction {
+            res_error(400, anyhow!("账单已关联还款，请先删除还款"))?;
+            return Ok(());
+        }
+        info.direction = Set(direction);
+    }
+    if let Some(currency) = req.form::<String>("currency").await {
+        let currency = parse_currency(&currency)?;
+        ensure_currency(db, &currency).await?;
+        if (claim_id.is_some() || reimbursed || reimbursing) && info.currency.as_ref() != &currency
+        {
+            res_error(400, anyhow!("账单已加入报销单，不能修改币种"))?;
+            return Ok(());
+        }
+        if repaid && info.currency.as_ref() != &currency {
+            res_error(400, anyhow!("账单已关联还款，请先删除还款"))?;
+            return Ok(());
+        }
+        info.currency = Set(currency);
+    }
+    // 取消可报销时一并移出报销单，已报销的账单须先撤销报销
+    if let Some(reimbursable) = req.form::<i8>("reimbursable").await.map(|v| (v > 0) as i8) {
+        if reimbursed && reimbursable == 0 {
+            res_error(400, anyhow!("账单已报销，请先撤销报销"))?;
+            return Ok(());
+        }
+        info.reimbursable = Set(reimbursable);
+        if reimbursable == 0 {
+            info.claim_id = Set(None);
+        }
+    }
+    if *info.reimbursable.as_ref() == 1 && *info.direction.as_ref() != Direction::Expense {
+        res_error(400, anyhow!("只有支出可以报销"))?;
+        return Ok(());
+    }
+    if let Some(tag_id) = req.form::<i32>("tag_id").await {
+        if TagTb::find()
+            .filter(tag_tb::Column::Id.eq(tag_id))
+            .filter(tag_tb::Column::LedgerId.eq(ledger_id))
+            .one(db)
+            .await
+            .json_err()?
+            .is_none()
+        {
+            res_error(400, anyhow!("无效的标签"))?;
+            return Ok(());
+        }
+        info.tag_id = Set(Some(tag_id));
+    }
+    // 账户须属于记账人，其他成员修改账单时也不能换成自己的账户
+    if let Some(account_id) = req.form::<i32>("account_id").await {
+        if AccountTb::find()
+            .filter(account_tb::Column::Id.eq(account_id))
+            .filter(account_tb::Column::UserId.eq(recorder_id))
+            .one(db)
+            .await
+            .json_err()?
+            .is_none()
+        {
+            res_error(400, anyhow!("无效的账户"))?;
+            return Ok(());
+        }
+        info.account_id = Set(account_id);
+    }
+    info.updated_time = Set(Local::now().naive_local());
+    info.update(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn update_tag(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let tag_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的标签ID")))?;
+    let name = req
+        .form::<String>("name")
+        .await
+        .filter(|s| !s.is_empty())
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到有效标签")))?;
+    let db = orm::get_dao()?;
+    let Some(info) = ledger_tag(db, user_id, tag_id).await? else {
+        res_error(400, anyhow!("无效的标签"))?;
+        return Ok(());
+    };
+    let ledger_id = info.ledger_id;
+    if TagTb::find()
+        .filter(tag_tb::Column::LedgerId.eq(ledger_id))
+        .filter(tag_tb::Column::Name.eq(&name))
+        .filter(tag_tb::Column::Id.ne(tag_id))
+        .count(db)
         .await
         .json_err()?
+        != 0
+    {
+        res_error(400, anyhow!("标签已存在"))?;
+        return Ok(());
+    }
+    let mut info = info.into_active_model();
+    // parent_id 传 0 表示改为一级标签
+    match req.form::<i32>("parent_id").await {
+        Some(0) => info.parent_id = Set(None),
+        Some(parent_id) => {
+            if parent_id == tag_id {
+                res_error(400, anyhow!("上级标签不能是自身"))?;
+                return Ok(());
+            }
+            let parent = parent_tag(db, ledger_id, parent_id).await?;
+            if TagTb::find()
+                .filter(tag_tb::Column::ParentId.eq(tag_id))
+                .count(db)
+                .await
+                .json_err()?
+                != 0
+            {
+                res_error(400, anyhow!("该标签已有子标签，不能再设置上级标签"))?;
+                return Ok(());
+            }
+            info.parent_id = Set(Some(parent.id));
+        }
+        None => {}
+    }
+    if let Some(icon) = req.form::<String>("icon").await {
+        info.icon = Set(Some(icon).filter(|s| !s.is_empty()));
+    }
+    if let Some(color) = req.form::<String>("color").await {
+        info.color = Set(match color.as_str() {
+            "" => None,
+            color => Some(parse_color(color)?),
+        });
+    }
+    info.name = Set(name);
+    info.updated_time = Set(Local::now().naive_local());
+    info.update(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 批量调整标签顺序，`ids` 为逗号分隔的标签ID，按先后顺序重新编号
+#[handler]
+pub async fn reorder_tag(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let ids = req
+        .form::<String>("ids")
+        .await
+        .filter(|s| !s.is_empty())
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到标签ID")))?;
+    let ids = ids
+        .split(',')
+        .map(|id| id.trim().parse::<i32>())
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| JsonErr::from_error(400, anyhow!("无效的标签ID：{e}")))?;
+    if ids.iter().collect::<HashSet<_>>().len() != ids.len() {
+        res_error(400, anyhow!("标签ID重复"))?;
+        return Ok(());
+    }
+    let db = orm::get_dao()?;
+    let ledger_id = ledger_access(db, user_id, req.form::<i32>("ledger_id").await, true).await?;
+    if TagTb::find()
+        .filter(tag_tb::Column::Id.is_in(ids.clone()))
+        .filter(tag_tb::Column::LedgerId.eq(ledger_id))
+        .count(db)
+        .await
+        .json_err()?
+        != ids.len() as u64
     {
-        let info = info.into_active_model();
-        info.delete(db).await.json_err()?;
-        res.render(Text::Json(
-            json!({
-                "status":"success",
-                "code":200,
-                "msg":"删除成功"
-            })
-            .to_string(),
-        ));
-    } else {
         res_error(400, anyhow!("无效的标签"))?;
+        return Ok(());
     }
+    let txn = db.begin().await.json_err()?;
+    for (position, id) in ids.into_iter().enumerate() {
+        TagTb::update_many()
+            .col_expr(tag_tb::Column::Position, Expr::value(position as i32))
+            .filter(tag_tb::Column::Id.eq(id))
+            .exec(&txn)
+            .await
+            .json_err()?;
+    }
+    txn.commit().await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
     Ok(())
 }
@@ -0,0 +1,234 @@
+use crate::bill::{line_sum, line_tag_in, split_lines};
+use crate::error::*;
+use crate::ledger::personal_ledger;
+use crate::orm::{
+    self,
+    model::{
+        prelude::*,
+        sea_orm_active_enums::{Direction, Period},
+        *,
+    },
+};
+use anyhow::anyhow;
+use chrono::{Datelike, Local, NaiveDate};
+use rust_decimal::prelude::*;
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveEnum, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait,
+    IntoActiveModel, QueryFilter, QuerySelect,
+};
+use serde_json::json;
+
+fn parse_period(period: &str) -> JsonResult<Period> {
+    match period {
+        "month" => Ok(Period::Month),
+        "year" => Ok(Period::Year),
+        _ => Err(JsonErr::from_error(400, anyhow!("无效的预算周期"))),
+    }
+}
+
+/// 当前所处预算周期的起止日期（含首尾）
+fn period_range(period: &Period, today: NaiveDate) -> (NaiveDate, NaiveDate) {
+    match period {
+        Period::Month => {
+            let begin = today.with_day(1).unwrap_or(today);
+            let end = begin
+                .checked_add_months(chrono::Months::new(1))
+                .and_then(|d| d.pred_opt())
+                .unwrap_or(today);
+            (begin, end)
+        }
+        Period::Year => {
+            let begin = NaiveDate::from_ymd_opt(today.year(), 1, 1).unwrap_or(today);
+            let end = NaiveDate::from_ymd_opt(today.year(), 12, 31).unwrap_or(today);
+            (begin, end)
+        }
+    }
+}
+
+/// 设置预算，同一标签同一周期只保留一条，重复设置即覆盖金额
+#[handler]
+pub async fn set_budget(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let amount = req
+        .form::<String>("amount")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到预算金额")))?;
+    let amount = Decimal::from_str(&amount)
+        .ok()
+        .filter(|v| v.is_sign_positive() && !v.is_zero())
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的预算金额")))?;
+    let period = req
+        .form::<String>("period")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到预算周期")))?;
+    let period = parse_period(&period)?;
+    let tag_id = req.form::<i32>("tag_id").await;
+
+    let db = orm::get_dao()?;
+    if let Some(tag_id) = tag_id
+        && TagTb::find()
+            .filter(tag_tb::Column::Id.eq(tag_id))
+            .filter(tag_tb::Column::LedgerId.eq(personal_ledger(db, user_id).await.json_err()?))
+            .one(db)
+            .await
+            .json_err()?
+            .is_none()
+    {
+        res_error(400, anyhow!("无效的标签"))?;
+        return Ok(());
+    }
+
+    let exist = BudgetTb::find()
+        .filter(budget_tb::Column::UserId.eq(user_id))
+        .filter(match tag_id {
+            Some(tag_id) => budget_tb::Column::TagId.eq(tag_id),
+            None => budget_tb::Column::TagId.is_null(),
+        })
+        .filter(budget_tb::Column::Period.eq(period.clone()))
+        .one(db)
+        .await
+        .json_err()?;
+    let now = Local::now().naive_local();
+    match exist {
+        Some(info) => {
+            let mut info = info.into_active_model();
+            info.amount = Set(amount);
+            info.updated_time = Set(now);
+            info.update(db).await.json_err()?;
+        }
+        None => {
+            let mut info = budget_tb::ActiveModel::new();
+            info.user_id = Set(user_id);
+            info.tag_id = Set(tag_id);
+            info.period = Set(period);
+            info.amount = Set(amount);
+            info.created_time = Set(now);
+            info.updated_time = Set(now);
+            info.insert(db).await.json_err()?;
+        }
+    }
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"设置成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn del_budget(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let budget_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的预算ID")))?;
+    let db = orm::get_dao()?;
+    if let Some(info) = BudgetTb::find()
+        .filter(budget_tb::Column::Id.eq(budget_id))
+        .filter(budget_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+    {
+        let info = info.into_active_model();
+        info.delete(db).await.json_err()?;
+        res.render(Text::Json(
+            json!({
+                "status":"success",
+                "code":200,
+                "msg":"删除成功"
+            })
+            .to_string(),
+        ));
+    } else {
+        res_error(400, anyhow!("无效的预算"))?;
+    }
+    Ok(())
+}
+
+/// 各预算在当前周期内的支出、剩余额度与使用比例，按个人账本统计
+#[handler]
+pub async fn budget_status(
+    _req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let db = orm::get_dao()?;
+    let ledger_id = personal_ledger(db, user_id).await.json_err()?;
+    let budgets = BudgetTb::find()
+        .filter(budget_tb::Column::UserId.eq(user_id))
+        .find_also_related(TagTb)
+        .all(db)
+        .await
+        .json_err()?;
+    let today = Local::now().date_naive();
+    let mut list = Vec::with_capacity(budgets.len());
+    for (budget, tag) in budgets {
+        let (begin, end) = period_range(&budget.period, today);
+        // 拆分账单按明细各自的标签计入预算
+        let mut select = split_lines(BillTb::find())
+            .select_only()
+            .column_as(line_sum(Direction::Expense, user_id), "spent")
+            .filter(bill_tb::Column::LedgerId.eq(ledger_id))
+            .filter(bill_tb::Column::TransactionDate.between(begin, end));
+        if let Some(tag_id) = budget.tag_id {
+            select = select.filter(line_tag_in(vec![tag_id]));
+        }
+        let spent = select
+            .into_tuple::<Option<Decimal>>()
+            .one(db)
+            .await
+            .json_err()?
+            .flatten()
+            .unwrap_or_default();
+        let percent = (spent * Decimal::ONE_HUNDRED)
+            .checked_div(budget.amount)
+            .unwrap_or_default()
+            .round_dp(2);
+        list.push(json!({
+            "id":budget.id,
+            "tag_id":budget.tag_id,
+            "tag_name":tag.map(|t| t.name),
+            "period":budget.period.to_value(),
+            "begin":begin,
+            "end":end,
+            "amount":budget.amount,
+            "spent":spent,
+            "remaining":budget.amount - spent,
+            "percent":percent,
+            "over_budget":spent > budget.amount
+        }));
+    }
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
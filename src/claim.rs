@@ -0,0 +1,573 @@
+use crate::error::*;
+use crate::orm::{
+    self,
+    model::{prelude::*, sea_orm_active_enums::Direction, *},
+};
+use crate::rate::{base_currency, ensure_currency, parse_currency};
+use anyhow::anyhow;
+use chrono::{Local, NaiveDate};
+use rust_decimal::Decimal;
+use salvo::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, DbErr, EntityTrait, FromQueryResult, IntoActiveModel, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait, sea_query::Expr,
+};
+use serde_json::json;
+use std::collections::HashSet;
+
+async fn user_claim(
+    db: &DatabaseConnection,
+    user_id: i32,
+    claim_id: i32,
+) -> JsonResult<claim_tb::Model> {
+    ClaimTb::find()
+        .filter(claim_tb::Column::Id.eq(claim_id))
+        .filter(claim_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的报销单")))
+}
+
+/// 尚未报销的可报销支出
+fn pending() -> sea_orm::Select<BillTb> {
+    BillTb::find()
+        .filter(bill_tb::Column::Reimbursable.eq(1))
+        .filter(bill_tb::Column::ReimbursedBy.is_null())
+}
+
+/// 收入账单到账时自动核销报销：先找待报销金额合计与收入相等的报销单，
+/// 整单标记为已报销；没有则找金额相等、未归入报销单的单笔可报销支出。
+/// 都按时间最早的优先，返回标记为已报销的账单数
+pub(crate) async fn match_reimbursement(
+    db: &impl ConnectionTrait,
+    income: &bill_tb::Model,
+) -> Result<u64, DbErr> {
+    let Some(pay) = income.pay else {
+        return Ok(0);
+    };
+    if income.direction != Direction::Income || income.transfer_id.is_some() {
+        return Ok(0);
+    }
+    let claim_id = pending()
+        .select_only()
+        .column(bill_tb::Column::ClaimId)
+        .filter(bill_tb::Column::UserId.eq(income.user_id))
+        .filter(bill_tb::Column::Currency.eq(&income.currency))
+        .filter(bill_tb::Column::ClaimId.is_not_null())
+        .filter(bill_tb::Column::TransactionDate.lte(income.transaction_date))
+        .group_by(bill_tb::Column::ClaimId)
+        .having(bill_tb::Column::Pay.sum().eq(pay))
+        .order_by_asc(bill_tb::Column::ClaimId)
+        .into_tuple::<Option<i32>>()
+        .one(db)
+        .await?
+        .flatten();
+    let mut select = pending()
+        .filter(bill_tb::Column::UserId.eq(income.user_id))
+        .filter(bill_tb::Column::Currency.eq(&income.currency));
+    if let Some(claim_id) = claim_id {
+        select = select.filter(bill_tb::Column::ClaimId.eq(claim_id));
+    } else {
+        let Some(bill) = select
+            .filter(bill_tb::Column::ClaimId.is_null())
+            .filter(bill_tb::Column::Pay.eq(pay))
+            .filter(bill_tb::Column::TransactionDate.lte(income.transaction_date))
+            .order_by_asc(bill_tb::Column::TransactionDate)
+            .order_by_asc(bill_tb::Column::Id)
+            .one(db)
+            .await?
+        else {
+            return Ok(0);
+        };
+        select = BillTb::find().filter(bill_tb::Column::Id.eq(bill.id));
+    }
+    let ids = select
+        .select_only()
+        .column(bill_tb::Column::Id)
+        .into_tuple::<i32>()
+        .all(db)
+        .await?;
+    Ok(BillTb::update_many()
+        .col_expr(bill_tb::Column::ReimbursedBy, Expr::value(income.id))
+        .filter(bill_tb::Column::Id.is_in(ids))
+        .exec(db)
+        .await?
+        .rows_affected)
+}
+
+/// 报销单列表，附带账单数、合计金额和已报销金额；全部账单都已报销时 `status` 为 `reimbursed`
+#[handler]
+pub async fn claim_list(
+    _req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let db = orm::get_dao()?;
+    let mut list = ClaimTb::find()
+        .filter(claim_tb::Column::UserId.eq(user_id))
+        .column_as(
+            Expr::cust(
+                "(SELECT COUNT(*) FROM `bill_tb` WHERE `bill_tb`.`claim_id` = `claim_tb`.`id`)",
+            ),
+            "billCount",
+        )
+        .column_as(
+            Expr::cust(
+                "(SELECT COALESCE(SUM(`bill_tb`.`pay`), 0) FROM `bill_tb` \
+                 WHERE `bill_tb`.`claim_id` = `claim_tb`.`id`)",
+            ),
+            "amount",
+        )
+        .column_as(
+            Expr::cust(
+                "(SELECT COALESCE(SUM(`bill_tb`.`pay`), 0) FROM `bill_tb` \
+                 WHERE `bill_tb`.`claim_id` = `claim_tb`.`id` AND `bill_tb`.`reimbursed_by` IS NOT NULL)",
+            ),
+            "reimbursedAmount",
+        )
+        .order_by_desc(claim_tb::Column::Id)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    for claim in &mut list {
+        let done = claim["billCount"] != 0 && claim["amount"] == claim["reimbursedAmount"];
+        claim["status"] = json!(if done { "reimbursed" } else { "open" });
+    }
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn add_claim(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let name = req
+        .form::<String>("name")
+        .await
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到报销单名称")))?;
+    let comment = req
+        .form::<String>("comment")
+        .await
+        .filter(|s| !s.is_empty());
+    let db = orm::get_dao()?;
+    let currency = match req.form::<String>("currency").await {
+        Some(currency) => {
+            let currency = parse_currency(&currency)?;
+            ensure_currency(db, &currency).await?;
+            currency
+        }
+        None => base_currency(db, user_id).await.json_err()?,
+    };
+    let mut info = claim_tb::ActiveModel::new();
+    info.user_id = Set(user_id);
+    info.name = Set(name);
+    info.currency = Set(currency);
+    info.comment = Set(comment);
+    let now = Local::now().naive_local();
+    info.created_time = Set(now);
+    info.updated_time = Set(now);
+    info.insert(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"新增成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 修改报销单名称和备注，币种不能修改
+#[handler]
+pub async fn update_claim(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let claim_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的报销单ID")))?;
+    let db = orm::get_dao()?;
+    let mut info = user_claim(db, user_id, claim_id).await?.into_active_model();
+    if let Some(name) = req
+        .form::<String>("name")
+        .await
+        .map(|s| s.trim().to_owned())
+    {
+        if name.is_empty() {
+            res_error(400, anyhow!("未获取到报销单名称"))?;
+            return Ok(());
+        }
+        info.name = Set(name);
+    }
+    if let Some(comment) = req.form::<String>("comment").await {
+        info.comment = Set(Some(comment).filter(|s| !s.is_empty()));
+    }
+    info.updated_time = Set(Local::now().naive_local());
+    info.update(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 删除报销单，其中的账单保留可报销标记和报销状态
+#[handler]
+pub async fn del_claim(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let claim_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的报销单ID")))?;
+    let db = orm::get_dao()?;
+    user_claim(db, user_id, claim_id)
+        .await?
+        .into_active_model()
+        .delete(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"删除成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 设置报销单包含的账单，整体替换。`bill_ids` 为 JSON 数组，如 `[1,2,3]`。
+/// 账单须为本人记录的支出，币种与报销单一致且不在其他报销单中，加入后自动标记为可报销
+#[handler]
+pub async fn set_claim_bills(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let claim_id = req
+        .form::<i32>("claim_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的报销单ID")))?;
+    let bill_ids = req
+        .form::<String>("bill_ids")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到报销账单")))?;
+    let bill_ids = serde_json::from_str::<HashSet<i32>>(&bill_ids)
+        .map_err(|e| JsonErr::from_error(400, anyhow!("报销账单解析错误：{e}")))?;
+    let db = orm::get_dao()?;
+    let claim = user_claim(db, user_id, claim_id).await?;
+    let bills = BillTb::find()
+        .filter(bill_tb::Column::Id.is_in(bill_ids.iter().copied()))
+        .filter(bill_tb::Column::UserId.eq(user_id))
+        .filter(bill_tb::Column::Direction.eq(Direction::Expense))
+        .filter(bill_tb::Column::TransferId.is_null())
+        .all(db)
+        .await
+        .json_err()?;
+    if bills.len() != bill_ids.len() {
+        res_error(400, anyhow!("只能报销本人记录的支出账单"))?;
+        return Ok(());
+    }
+    if bills.iter().any(|bill| bill.currency != claim.currency) {
+        res_error(400, anyhow!("账单币种须与报销单一致"))?;
+        return Ok(());
+    }
+    if bills
+        .iter()
+        .any(|bill| bill.claim_id.is_some_and(|id| id != claim_id))
+    {
+        res_error(400, anyhow!("账单已在其他报销单中"))?;
+        return Ok(());
+    }
+
+    let txn = db.begin().await.json_err()?;
+    BillTb::update_many()
+        .col_expr(bill_tb::Column::ClaimId, Expr::value(Option::<i32>::None))
+        .filter(bill_tb::Column::ClaimId.eq(claim_id))
+        .exec(&txn)
+        .await
+        .json_err()?;
+    if !bill_ids.is_empty() {
+        BillTb::update_many()
+            .col_expr(bill_tb::Column::ClaimId, Expr::value(claim_id))
+            .col_expr(bill_tb::Column::Reimbursable, Expr::value(1))
+            .filter(bill_tb::Column::Id.is_in(bill_ids))
+            .exec(&txn)
+            .await
+            .json_err()?;
+    }
+    txn.commit().await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn claim_bills(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let claim_id = req
+        .query::<i32>("claim_id")
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的报销单ID")))?;
+    let db = orm::get_dao()?;
+    user_claim(db, user_id, claim_id).await?;
+    let list = BillTb::find()
+        .filter(bill_tb::Column::ClaimId.eq(claim_id))
+        .left_join(TagTb)
+        .column_as(tag_tb::Column::Name, "tagName")
+        .left_join(AccountTb)
+        .column_as(account_tb::Column::Name, "accountName")
+        .order_by_asc(bill_tb::Column::TransactionDate)
+        .order_by_asc(bill_tb::Column::Id)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 手动指定报销到账的收入账单，报销单中尚未报销的账单全部标记为已报销，
+/// 用于到账金额与报销单合计不一致、未能自动核销的情况。不传 `bill_id` 则撤销报销
+#[handler]
+pub async fn reimburse_claim(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let claim_id = req
+        .form::<i32>("claim_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的报销单ID")))?;
+    let db = orm::get_dao()?;
+    let claim = user_claim(db, user_id, claim_id).await?;
+    let Some(bill_id) = req.form::<i32>("bill_id").await else {
+        BillTb::update_many()
+            .col_expr(
+                bill_tb::Column::ReimbursedBy,
+                Expr::value(Option::<i32>::None),
+            )
+            .filter(bill_tb::Column::ClaimId.eq(claim_id))
+            .exec(db)
+            .await
+            .json_err()?;
+        res.render(Text::Json(
+            json!({
+                "status":"success",
+                "code":200,
+                "msg":"修改成功"
+            })
+            .to_string(),
+        ));
+        return Ok(());
+    };
+    if BillTb::find()
+        .filter(bill_tb::Column::Id.eq(bill_id))
+        .filter(bill_tb::Column::UserId.eq(user_id))
+        .filter(bill_tb::Column::Direction.eq(Direction::Income))
+        .filter(bill_tb::Column::TransferId.is_null())
+        .filter(bill_tb::Column::Currency.eq(&claim.currency))
+        .count(db)
+        .await
+        .json_err()?
+        == 0
+    {
+        res_error(400, anyhow!("报销款须为本人记录的同币种收入账单"))?;
+        return Ok(());
+    }
+    if BillTb::find()
+        .filter(bill_tb::Column::ReimbursedBy.eq(bill_id))
+        .filter(
+            bill_tb::Column::ClaimId
+                .ne(claim_id)
+                .or(bill_tb::Column::ClaimId.is_null()),
+        )
+        .count(db)
+        .await
+        .json_err()?
+        != 0
+    {
+        res_error(400, anyhow!("该收入账单已用于其他报销"))?;
+        return Ok(());
+    }
+    BillTb::update_many()
+        .col_expr(bill_tb::Column::ReimbursedBy, Expr::value(bill_id))
+        .filter(bill_tb::Column::ClaimId.eq(claim_id))
+        .filter(bill_tb::Column::ReimbursedBy.is_null())
+        .exec(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[derive(FromQueryResult)]
+struct ClaimRow {
+    transaction_date: NaiveDate,
+    pay: Option<Decimal>,
+    currency: String,
+    account_name: Option<String>,
+    tag_name: Option<String>,
+    comment: Option<String>,
+    reimbursed_by: Option<i32>,
+}
+
+/// 以 CSV 导出报销单明细，末行为合计，格式与 `bill/export` 一致
+#[handler]
+pub async fn claim_export(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let claim_id = req
+        .query::<i32>("claim_id")
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的报销单ID")))?;
+    let db = orm::get_dao()?;
+    let claim = user_claim(db, user_id, claim_id).await?;
+    let rows = BillTb::find()
+        .select_only()
+        .columns([
+            bill_tb::Column::TransactionDate,
+            bill_tb::Column::Pay,
+            bill_tb::Column::Currency,
+            bill_tb::Column::Comment,
+            bill_tb::Column::ReimbursedBy,
+        ])
+        .filter(bill_tb::Column::ClaimId.eq(claim_id))
+        .left_join(TagTb)
+        .column_as(tag_tb::Column::Name, "tag_name")
+        .left_join(AccountTb)
+        .column_as(account_tb::Column::Name, "account_name")
+        .order_by_asc(bill_tb::Column::TransactionDate)
+        .order_by_asc(bill_tb::Column::Id)
+        .into_model::<ClaimRow>()
+        .all(db)
+        .await
+        .json_err()?;
+
+    let total = rows.iter().filter_map(|row| row.pay).sum::<Decimal>();
+    let mut buf = "\u{FEFF}".as_bytes().to_vec();
+    {
+        let mut writer = csv::WriterBuilder::new()
+            .terminator(csv::Terminator::CRLF)
+            .from_writer(&mut buf);
+        let mut write = |record: [String; 7]| {
+            writer
+                .write_record(record)
+                .map_err(|e| JsonErr::from_error(500, anyhow!(e)))
+        };
+        write(["交易日期", "金额", "币种", "账户", "标签", "备注", "状态"].map(str::to_owned))?;
+        for row in rows {
+            write([
+                row.transaction_date.format("%Y-%m-%d").to_string(),
+                row.pay.map(|v| v.to_string()).unwrap_or_default(),
+                row.currency,
+                row.account_name.unwrap_or_default(),
+                row.tag_name.unwrap_or_default(),
+                row.comment.unwrap_or_default(),
+                if row.reimbursed_by.is_some() {
+                    "已报销"
+                } else {
+                    "待报销"
+                }
+                .to_owned(),
+            ])?;
+        }
+        write([
+            "合计".to_owned(),
+            total.to_string(),
+            claim.currency,
+            String::new(),
+            String::new(),
+            claim.name,
+            String::new(),
+        ])?;
+        writer
+            .flush()
+            .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+    }
+
+    res.add_header(CONTENT_TYPE, "text/csv; charset=utf-8", true)
+        .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+    res.add_header(
+        CONTENT_DISPOSITION,
+        format!("attachment; filename=\"claim_{claim_id}.csv\""),
+        true,
+    )
+    .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+    res.write_body(buf)
+        .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+    Ok(())
+}
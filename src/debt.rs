@@ -0,0 +1,551 @@
+use crate::error::*;
+use crate::orm::{
+    self,
+    model::{
+        prelude::*,
+        sea_orm_active_enums::{DebtDirection, Direction},
+        *,
+    },
+};
+use crate::rate::{base_currency, ensure_currency, parse_currency};
+use anyhow::anyhow;
+use chrono::{Local, NaiveDate};
+use rust_decimal::prelude::*;
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveEnum, ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait,
+    DatabaseConnection, DbErr, EntityTrait, FromQueryResult, IntoActiveModel, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, sea_query::Expr,
+};
+use serde_json::json;
+use std::collections::BTreeMap;
+
+/// 已还金额，取关联账单的金额之和
+const REPAID: &str = "(SELECT COALESCE(SUM(`b`.`pay`), 0) FROM `debt_repayment_tb` `r` \
+     INNER JOIN `bill_tb` `b` ON `b`.`id` = `r`.`bill_id` WHERE `r`.`debt_id` = `debt_tb`.`id`)";
+
+fn parse_debt_direction(direction: &str) -> JsonResult<DebtDirection> {
+    match direction {
+        "lend" => Ok(DebtDirection::Lend),
+        "borrow" => Ok(DebtDirection::Borrow),
+        _ => Err(JsonErr::from_error(400, anyhow!("无效的借贷方向"))),
+    }
+}
+
+fn parse_principal(principal: &str) -> JsonResult<Decimal> {
+    Decimal::from_str(principal)
+        .ok()
+        .filter(|v| v.is_sign_positive() && !v.is_zero())
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的借款金额")))
+}
+
+fn parse_day(date: &str, label: &str) -> JsonResult<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d")
+        .map_err(|e| JsonErr::from_error(400, anyhow!("{label}解析错误：{}", e)))
+}
+
+async fn user_debt(
+    db: &DatabaseConnection,
+    user_id: i32,
+    debt_id: i32,
+) -> JsonResult<debt_tb::Model> {
+    DebtTb::find()
+        .filter(debt_tb::Column::Id.eq(debt_id))
+        .filter(debt_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的借贷")))
+}
+
+async fn repaid(db: &DatabaseConnection, debt_id: i32) -> Result<Decimal, DbErr> {
+    Ok(DebtRepaymentTb::find()
+        .inner_join(BillTb)
+        .select_only()
+        .column_as(bill_tb::Column::Pay.sum(), "repaid")
+        .filter(debt_repayment_tb::Column::DebtId.eq(debt_id))
+        .into_tuple::<Option<Decimal>>()
+        .one(db)
+        .await?
+        .flatten()
+        .unwrap_or_default())
+}
+
+/// 借贷列表，`status` 为 `open`（未还清）、`closed`（已还清），不传返回全部
+#[handler]
+pub async fn debt_list(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let mut select = DebtTb::find()
+        .filter(debt_tb::Column::UserId.eq(user_id))
+        .column_as(Expr::cust(REPAID), "repaid")
+        .column_as(
+            Expr::cust(format!("`debt_tb`.`principal` - {REPAID}")),
+            "outstanding",
+        );
+    match req.query::<String>("status").unwrap_or_default().as_str() {
+        "" => {}
+        "open" => select = select.filter(Expr::cust(format!("{REPAID} < `debt_tb`.`principal`"))),
+        "closed" => {
+            select = select.filter(Expr::cust(format!("{REPAID} >= `debt_tb`.`principal`")))
+        }
+        _ => {
+            res_error(400, anyhow!("无效的借贷状态"))?;
+            return Ok(());
+        }
+    }
+    if let Some(direction) = req.query::<String>("direction").filter(|s| !s.is_empty()) {
+        select = select.filter(debt_tb::Column::Direction.eq(parse_debt_direction(&direction)?));
+    }
+    let db = orm::get_dao()?;
+    let list = select
+        .order_by_desc(debt_tb::Column::DebtDate)
+        .order_by_desc(debt_tb::Column::Id)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn add_debt(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let counterparty = req
+        .form::<String>("counterparty")
+        .await
+        .map(|s| s.trim().to_owned())
+        .filter(|s| !s.is_empty())
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到对方姓名")))?;
+    let direction = req
+        .form::<String>("direction")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到借贷方向")))?;
+    let direction = parse_debt_direction(&direction)?;
+    let principal = req
+        .form::<String>("principal")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到借款金额")))?;
+    let principal = parse_principal(&principal)?;
+    let debt_date = match req.form::<String>("debt_date").await {
+        Some(date) => parse_day(&date, "借款日期")?,
+        None => Local::now().date_naive(),
+    };
+    let due_date = match req
+        .form::<String>("due_date")
+        .await
+        .filter(|s| !s.is_empty())
+    {
+        Some(date) => Some(parse_day(&date, "还款日期")?),
+        None => None,
+    };
+    if due_date.is_some_and(|due| due < debt_date) {
+        res_error(400, anyhow!("还款日期不能早于借款日期"))?;
+        return Ok(());
+    }
+    let comment = req
+        .form::<String>("comment")
+        .await
+        .filter(|s| !s.is_empty());
+
+    let db = orm::get_dao()?;
+    let currency = match req.form::<String>("currency").await {
+        Some(currency) => {
+            let currency = parse_currency(&currency)?;
+            ensure_currency(db, &currency).await?;
+            currency
+        }
+        None => base_currency(db, user_id).await.json_err()?,
+    };
+    let mut info = debt_tb::ActiveModel::new();
+    info.user_id = Set(user_id);
+    info.counterparty = Set(counterparty);
+    info.direction = Set(direction);
+    info.principal = Set(principal);
+    info.currency = Set(currency);
+    info.debt_date = Set(debt_date);
+    info.due_date = Set(due_date);
+    info.comment = Set(comment);
+    let now = Local::now().naive_local();
+    info.created_time = Set(now);
+    info.updated_time = Set(now);
+    info.insert(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"新增成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 修改借贷。借贷方向和币种不能修改，本金不能少于已还金额；`due_date` 传空清除
+#[handler]
+pub async fn update_debt(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let debt_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的借贷ID")))?;
+    let db = orm::get_dao()?;
+    let mut info = user_debt(db, user_id, debt_id).await?.into_active_model();
+    if let Some(counterparty) = req
+        .form::<String>("counterparty")
+        .await
+        .map(|s| s.trim().to_owned())
+    {
+        if counterparty.is_empty() {
+            res_error(400, anyhow!("未获取到对方姓名"))?;
+            return Ok(());
+        }
+        info.counterparty = Set(counterparty);
+    }
+    if let Some(principal) = req.form::<String>("principal").await {
+        let principal = parse_principal(&principal)?;
+        let repaid = repaid(db, debt_id).await.json_err()?;
+        if principal < repaid {
+            res_error(400, anyhow!("借款金额不能少于已还金额 {repaid}"))?;
+            return Ok(());
+        }
+        info.principal = Set(principal);
+    }
+    if let Some(debt_date) = req.form::<String>("debt_date").await {
+        info.debt_date = Set(parse_day(&debt_date, "借款日期")?);
+    }
+    if let Some(due_date) = req.form::<String>("due_date").await {
+        info.due_date = Set(match due_date.as_str() {
+            "" => None,
+            date => Some(parse_day(date, "还款日期")?),
+        });
+    }
+    if info
+        .due_date
+        .as_ref()
+        .is_some_and(|due| due < *info.debt_date.as_ref())
+    {
+        res_error(400, anyhow!("还款日期不能早于借款日期"))?;
+        return Ok(());
+    }
+    if let Some(comment) = req.form::<String>("comment").await {
+        info.comment = Set(Some(comment).filter(|s| !s.is_empty()));
+    }
+    info.updated_time = Set(Local::now().naive_local());
+    info.update(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 删除借贷，还款关联随之删除，关联的账单保留
+#[handler]
+pub async fn del_debt(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let debt_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的借贷ID")))?;
+    let db = orm::get_dao()?;
+    user_debt(db, user_id, debt_id)
+        .await?
+        .into_active_model()
+        .delete(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"删除成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 将账单登记为借贷的一笔还款。借出的还款为收入账单，借入的还款为支出账单，
+/// 币种须与借贷一致，金额不能超过未还金额
+#[handler]
+pub async fn add_repayment(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let debt_id = req
+        .form::<i32>("debt_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的借贷ID")))?;
+    let bill_id = req
+        .form::<i32>("bill_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账单ID")))?;
+    let db = orm::get_dao()?;
+    let debt = user_debt(db, user_id, debt_id).await?;
+    let Some(bill) = BillTb::find()
+        .filter(bill_tb::Column::Id.eq(bill_id))
+        .filter(bill_tb::Column::UserId.eq(user_id))
+        .filter(bill_tb::Column::TransferId.is_null())
+        .one(db)
+        .await
+        .json_err()?
+    else {
+        res_error(400, anyhow!("无效的账单"))?;
+        return Ok(());
+    };
+    let expected = match debt.direction {
+        DebtDirection::Lend => Direction::Income,
+        DebtDirection::Borrow => Direction::Expense,
+    };
+    if bill.direction != expected {
+        res_error(
+            400,
+            anyhow!(match debt.direction {
+                DebtDirection::Lend => "借出的还款须为收入账单",
+                DebtDirection::Borrow => "借入的还款须为支出账单",
+            }),
+        )?;
+        return Ok(());
+    }
+    if bill.currency != debt.currency {
+        res_error(400, anyhow!("账单币种须与借贷一致"))?;
+        return Ok(());
+    }
+    if DebtRepaymentTb::find()
+        .filter(debt_repayment_tb::Column::BillId.eq(bill_id))
+        .count(db)
+        .await
+        .json_err()?
+        != 0
+    {
+        res_error(400, anyhow!("该账单已登记为还款"))?;
+        return Ok(());
+    }
+    let outstanding = debt.principal - repaid(db, debt_id).await.json_err()?;
+    if bill.pay.unwrap_or_default() > outstanding {
+        res_error(400, anyhow!("还款金额超过未还金额 {outstanding}"))?;
+        return Ok(());
+    }
+    let mut info = debt_repayment_tb::ActiveModel::new();
+    info.debt_id = Set(debt_id);
+    info.bill_id = Set(bill_id);
+    info.created_time = Set(Local::now().naive_local());
+    info.insert(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"新增成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn repayment_list(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let debt_id = req
+        .query::<i32>("debt_id")
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的借贷ID")))?;
+    let db = orm::get_dao()?;
+    user_debt(db, user_id, debt_id).await?;
+    let list = DebtRepaymentTb::find()
+        .filter(debt_repayment_tb::Column::DebtId.eq(debt_id))
+        .inner_join(BillTb)
+        .column_as(bill_tb::Column::Pay, "pay")
+        .column_as(bill_tb::Column::TransactionDate, "transactionDate")
+        .column_as(bill_tb::Column::Comment, "comment")
+        .order_by_asc(bill_tb::Column::TransactionDate)
+        .order_by_asc(debt_repayment_tb::Column::Id)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 取消还款登记，账单本身保留
+#[handler]
+pub async fn del_repayment(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let repayment_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的还款ID")))?;
+    let db = orm::get_dao()?;
+    let Some((repayment, Some(_))) = DebtRepaymentTb::find_by_id(repayment_id)
+        .find_also_related(DebtTb)
+        .filter(debt_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+    else {
+        res_error(400, anyhow!("无效的还款"))?;
+        return Ok(());
+    };
+    repayment.into_active_model().delete(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"删除成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[derive(FromQueryResult)]
+struct OpenDebt {
+    id: i32,
+    counterparty: String,
+    direction: DebtDirection,
+    principal: Decimal,
+    currency: String,
+    debt_date: NaiveDate,
+    due_date: Option<NaiveDate>,
+    repaid: Decimal,
+}
+
+/// 未还清借贷的汇总：逐笔列出未还金额和是否逾期，
+/// 并按借出、借入和币种合计应收、应付
+#[handler]
+pub async fn debt_summary(
+    _req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let db = orm::get_dao()?;
+    let debts = DebtTb::find()
+        .select_only()
+        .columns([
+            debt_tb::Column::Id,
+            debt_tb::Column::Counterparty,
+            debt_tb::Column::Direction,
+            debt_tb::Column::Principal,
+            debt_tb::Column::Currency,
+            debt_tb::Column::DebtDate,
+            debt_tb::Column::DueDate,
+        ])
+        .column_as(Expr::cust(REPAID), "repaid")
+        .filter(debt_tb::Column::UserId.eq(user_id))
+        .filter(Expr::cust(format!("{REPAID} < `debt_tb`.`principal`")))
+        // 没有约定还款日期的排在最后
+        .order_by_asc(Expr::cust("`debt_tb`.`due_date` IS NULL"))
+        .order_by_asc(debt_tb::Column::DueDate)
+        .order_by_asc(debt_tb::Column::Id)
+        .into_model::<OpenDebt>()
+        .all(db)
+        .await
+        .json_err()?;
+
+    let today = Local::now().date_naive();
+    let mut totals = BTreeMap::<(String, String), Decimal>::new();
+    let list = debts
+        .into_iter()
+        .map(|debt| {
+            let outstanding = debt.principal - debt.repaid;
+            let direction = debt.direction.to_value();
+            *totals
+                .entry((direction.clone(), debt.currency.clone()))
+                .or_default() += outstanding;
+            json!({
+                "id":debt.id,
+                "counterparty":debt.counterparty,
+                "direction":direction,
+                "principal":debt.principal,
+                "currency":debt.currency,
+                "debt_date":debt.debt_date,
+                "due_date":debt.due_date,
+                "repaid":debt.repaid,
+                "outstanding":outstanding,
+                "overdue":debt.due_date.is_some_and(|due| due < today),
+                "days_until_due":debt.due_date.map(|due| (due - today).num_days())
+            })
+        })
+        .collect::<Vec<_>>();
+    let totals = totals
+        .into_iter()
+        .map(|((direction, currency), outstanding)| {
+            json!({
+                "direction":direction,
+                "currency":currency,
+                "outstanding":outstanding
+            })
+        })
+        .collect::<Vec<_>>();
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list,
+                    "totals":totals
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
@@ -0,0 +1,137 @@
+use crate::bill::{bill_filter, bill_order};
+use crate::error::*;
+use crate::ledger::ledger_access;
+use crate::orm::{
+    self,
+    model::{prelude::*, *},
+};
+use anyhow::anyhow;
+use chrono::NaiveDate;
+use rust_decimal::Decimal;
+use salvo::http::header::{CONTENT_DISPOSITION, CONTENT_TYPE};
+use salvo::prelude::*;
+use sea_orm::{FromQueryResult, PaginatorTrait, QuerySelect};
+
+/// 每批从数据库读取的行数
+const EXPORT_BATCH: u64 = 500;
+
+#[derive(FromQueryResult)]
+struct ExportRow {
+    transaction_date: NaiveDate,
+    direction: String,
+    pay: Option<Decimal>,
+    currency: String,
+    account_name: Option<String>,
+    tag_name: Option<String>,
+    comment: Option<String>,
+}
+
+fn write_csv(
+    buf: &mut Vec<u8>,
+    rows: impl IntoIterator<Item = [String; 7]>,
+) -> Result<(), csv::Error> {
+    let mut writer = csv::WriterBuilder::new()
+        .terminator(csv::Terminator::CRLF)
+        .from_writer(buf);
+    for row in rows {
+        writer.write_record(row)?;
+    }
+    writer.flush()?;
+    Ok(())
+}
+
+/// 以 CSV 导出账单，筛选条件与 `bill/list` 一致。
+/// 带 UTF-8 BOM，Excel 打开中文标签名和备注时不会乱码。
+#[handler]
+pub async fn bill_export(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let db = orm::get_dao()?;
+    let ledger_id = ledger_access(db, user_id, req.query::<i32>("ledger_id"), false).await?;
+    let select = bill_order(req, bill_filter(req, ledger_id)?)?;
+    let filename = format!(
+        "bills_{}_{}.csv",
+        req.query::<String>("begin").unwrap_or_default(),
+        req.query::<String>("end").unwrap_or_default()
+    );
+
+    let mut head = "\u{FEFF}".as_bytes().to_vec();
+    write_csv(
+        &mut head,
+        [[
+            "交易日期".to_owned(),
+            "收支".to_owned(),
+            "金额".to_owned(),
+            "币种".to_owned(),
+            "账户".to_owned(),
+            "标签".to_owned(),
+            "备注".to_owned(),
+        ]],
+    )
+    .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+
+    res.add_header(CONTENT_TYPE, "text/csv; charset=utf-8", true)
+        .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+    res.add_header(
+        CONTENT_DISPOSITION,
+        format!("attachment; filename=\"{filename}\""),
+        true,
+    )
+    .map_err(|e| JsonErr::from_error(500, anyhow!(e)))?;
+    let mut tx = res.channel();
+    tokio::spawn(async move {
+        if tx.send_data(head).await.is_err() {
+            return;
+        }
+        let mut paginator = select
+            .select_only()
+            .columns([
+                bill_tb::Column::TransactionDate,
+                bill_tb::Column::Direction,
+                bill_tb::Column::Pay,
+                bill_tb::Column::Currency,
+                bill_tb::Column::Comment,
+            ])
+            .left_join(TagTb)
+            .column_as(tag_tb::Column::Name, "tag_name")
+            .left_join(AccountTb)
+            .column_as(account_tb::Column::Name, "account_name")
+            .into_model::<ExportRow>()
+            .paginate(db, EXPORT_BATCH);
+        loop {
+            let rows = match paginator.fetch_and_next().await {
+                Ok(Some(rows)) => rows,
+                Ok(None) => break,
+                Err(e) => {
+                    tracing::error!("导出账单失败：{e}");
+                    break;
+                }
+            };
+            let mut buf = Vec::new();
+            let rows = rows.into_iter().map(|row| {
+                [
+                    row.transaction_date.format("%Y-%m-%d").to_string(),
+                    row.direction,
+                    row.pay.map(|v| v.to_string()).unwrap_or_default(),
+                    row.currency,
+                    row.account_name.unwrap_or_default(),
+                    row.tag_name.unwrap_or_default(),
+                    row.comment.unwrap_or_default(),
+                ]
+            });
+            if let Err(e) = write_csv(&mut buf, rows) {
+                tracing::error!("导出账单失败：{e}");
+                break;
+            }
+            if tx.send_data(buf).await.is_err() {
+                break;
+            }
+        }
+    });
+    Ok(())
+}
@@ -0,0 +1,434 @@
+use crate::account::guess_account_type;
+use crate::claim::match_reimbursement;
+use crate::error::*;
+use crate::ledger::ledger_access;
+use crate::orm::{
+    self,
+    model::{prelude::*, sea_orm_active_enums::Direction, *},
+};
+use crate::rate::base_currency;
+use anyhow::anyhow;
+use chrono::{Local, NaiveDate, NaiveDateTime};
+use rust_decimal::prelude::*;
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, DbErr, EntityTrait, QueryFilter, QueryOrder, TransactionTrait,
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// 单次批量插入的账单条数
+const INSERT_BATCH: usize = 500;
+
+/// 解析后待入库的一行账单
+pub(crate) struct ImportRow {
+    pub transaction_date: NaiveDate,
+    pub pay: Decimal,
+    pub direction: Direction,
+    pub account_name: String,
+    pub tag_name: String,
+    pub comment: Option<String>,
+    /// 第三方账单交易号，用于跳过已导入的记录
+    pub external_id: Option<String>,
+}
+
+pub(crate) fn row_error(line: usize, msg: impl std::fmt::Display) -> serde_json::Value {
+    json!({
+        "line":line,
+        "msg":msg.to_string()
+    })
+}
+
+pub(crate) fn parse_date(value: &str) -> Option<NaiveDate> {
+    let value = value.trim();
+    ["%Y-%m-%d", "%Y/%m/%d"]
+        .iter()
+        .find_map(|f| NaiveDate::parse_from_str(value, f).ok())
+        .or_else(|| {
+            [
+                "%Y-%m-%d %H:%M:%S",
+                "%Y/%m/%d %H:%M:%S",
+                "%Y-%m-%d %H:%M",
+                "%Y/%m/%d %H:%M",
+            ]
+            .iter()
+            .find_map(|f| NaiveDateTime::parse_from_str(value, f).ok())
+            .map(|d| d.date())
+        })
+}
+
+pub(crate) fn parse_amount(value: &str) -> Option<Decimal> {
+    let value = value
+        .trim()
+        .trim_start_matches(['¥', '￥'])
+        .replace(',', "");
+    Decimal::from_str(value.trim()).ok()
+}
+
+fn parse_direction_text(value: &str) -> Option<Direction> {
+    match value.trim() {
+        "" | "expense" | "支出" => Some(Direction::Expense),
+        "income" | "收入" => Some(Direction::Income),
+        _ => None,
+    }
+}
+
+/// 读取上传文件的原始内容
+pub(crate) async fn read_upload(req: &mut Request) -> JsonResult<Vec<u8>> {
+    let path = req
+        .file("file")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到上传文件")))?
+        .path()
+        .clone();
+    tokio::fs::read(path)
+        .await
+        .map_err(|e| JsonErr::from_error(500, anyhow!(e)))
+}
+
+/// 导入行引用的标签、账户与已有记录的对应关系，以及需要新建的名称
+pub(crate) struct Lookup {
+    /// 导入到的账本，标签在该账本中查找和新建
+    ledger_id: i32,
+    /// 标签名与账户名一样不区分大小写（数据库排序规则），键为小写
+    tags: HashMap<String, i32>,
+    pub new_tags: Vec<String>,
+    /// 账户名不区分大小写，键为小写
+    accounts: HashMap<String, i32>,
+    pub new_accounts: Vec<String>,
+}
+
+/// 按数据库列的排序规则查找同名标签，全角、重音等变体在数据库中视为同名
+async fn find_tag(
+    db: &impl ConnectionTrait,
+    ledger_id: i32,
+    name: &str,
+) -> Result<Option<i32>, DbErr> {
+    Ok(TagTb::find()
+        .filter(tag_tb::Column::LedgerId.eq(ledger_id))
+        .filter(tag_tb::Column::Name.eq(name))
+        .one(db)
+        .await?
+        .map(|tag| tag.id))
+}
+
+/// 按数据库列的排序规则查找同名账户，与 `user_account_name` 唯一索引的判断一致
+async fn find_account(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    name: &str,
+) -> Result<Option<i32>, DbErr> {
+    Ok(AccountTb::find()
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .filter(account_tb::Column::Name.eq(name))
+        .one(db)
+        .await?
+        .map(|account| account.id))
+}
+
+pub(crate) async fn lookup(
+    db: &DatabaseConnection,
+    user_id: i32,
+    ledger_id: i32,
+    rows: &[ImportRow],
+) -> Result<Lookup, DbErr> {
+    let mut tags = TagTb::find()
+        .filter(tag_tb::Column::LedgerId.eq(ledger_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|tag| (tag.name.to_lowercase(), tag.id))
+        .collect::<HashMap<_, _>>();
+    let mut accounts = AccountTb::find()
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .all(db)
+        .await?
+        .into_iter()
+        .map(|account| (account.name.to_lowercase(), account.id))
+        .collect::<HashMap<_, _>>();
+    let mut new_tags: Vec<String> = Vec::new();
+    let mut new_accounts: Vec<String> = Vec::new();
+    for row in rows {
+        let key = row.tag_name.to_lowercase();
+        if !tags.contains_key(&key) && !new_tags.iter().any(|t| t.to_lowercase() == key) {
+            match find_tag(db, ledger_id, &row.tag_name).await? {
+                Some(id) => {
+                    tags.insert(key, id);
+                }
+                None => new_tags.push(row.tag_name.clone()),
+            }
+        }
+        let key = row.account_name.to_lowercase();
+        if !accounts.contains_key(&key) && !new_accounts.iter().any(|a| a.to_lowercase() == key) {
+            match find_account(db, user_id, &row.account_name).await? {
+                Some(id) => {
+                    accounts.insert(key, id);
+                }
+                None => new_accounts.push(row.account_name.clone()),
+            }
+        }
+    }
+    Ok(Lookup {
+        ledger_id,
+        tags,
+        new_tags,
+        accounts,
+        new_accounts,
+    })
+}
+
+/// 在同一个事务中补建标签、账户并写入全部账单，任一步失败则整体回滚。
+/// 账户属于导入人，账单和标签归入 `lookup` 的账本
+pub(crate) async fn save_rows(
+    db: &DatabaseConnection,
+    user_id: i32,
+    rows: Vec<ImportRow>,
+    lookup: Lookup,
+) -> Result<usize, DbErr> {
+    let Lookup {
+        ledger_id,
+        mut tags,
+        new_tags,
+        mut accounts,
+        new_accounts,
+    } = lookup;
+    let txn = db.begin().await?;
+    let now = Local::now().naive_local();
+    let currency = base_currency(&txn, user_id).await?;
+    // 新名称之间也可能在数据库中视为同名，先查找本次已建的记录
+    for name in new_tags {
+        if let Some(id) = find_tag(&txn, ledger_id, &name).await? {
+            tags.insert(name.to_lowercase(), id);
+            continue;
+        }
+        let mut info = tag_tb::ActiveModel::new();
+        info.name = Set(name.clone());
+        info.user_id = Set(user_id);
+        info.ledger_id = Set(ledger_id);
+        info.created_time = Set(now);
+        info.updated_time = Set(now);
+        let info = info.insert(&txn).await?;
+        tags.insert(name.to_lowercase(), info.id);
+    }
+    for name in new_accounts {
+        if let Some(id) = find_account(&txn, user_id, &name).await? {
+            accounts.insert(name.to_lowercase(), id);
+            continue;
+        }
+        let mut info = account_tb::ActiveModel::new();
+        info.user_id = Set(user_id);
+        info.account_type = Set(guess_account_type(&name));
+        info.opening_balance = Set(Decimal::ZERO);
+        info.name = Set(name.clone());
+        info.created_time = Set(now);
+        info.updated_time = Set(now);
+        let info = info.insert(&txn).await?;
+        accounts.insert(name.to_lowercase(), info.id);
+    }
+    let total = rows.len();
+    let mut bills = Vec::with_capacity(total);
+    for row in rows {
+        let mut info = bill_tb::ActiveModel::new();
+        info.comment = Set(row.comment);
+        info.pay = Set(Some(row.pay));
+        info.account_id = Set(accounts[&row.account_name.to_lowercase()]);
+        info.transaction_date = Set(row.transaction_date);
+        info.user_id = Set(user_id);
+        info.ledger_id = Set(ledger_id);
+        info.tag_id = Set(Some(tags[&row.tag_name.to_lowercase()]));
+        info.direction = Set(row.direction);
+        info.external_id = Set(row.external_id);
+        info.currency = Set(currency.clone());
+        info.created_time = Set(now);
+        info.updated_time = Set(now);
+        bills.push(info);
+    }
+    // 收入逐条插入以取得账单id，支出分批插入
+    let (incomes, mut bills): (Vec<_>, Vec<_>) = bills
+        .into_iter()
+        .partition(|info| *info.direction.as_ref() == Direction::Income);
+    while !bills.is_empty() {
+        let rest = bills.split_off(bills.len().min(INSERT_BATCH));
+        BillTb::insert_many(bills).exec(&txn).await?;
+        bills = rest;
+    }
+    let mut income_ids = Vec::with_capacity(incomes.len());
+    for info in incomes {
+        income_ids.push(BillTb::insert(info).exec(&txn).await?.last_insert_id);
+    }
+    // 导入的收入按日期依次尝试核销报销
+    let incomes = BillTb::find()
+        .filter(bill_tb::Column::Id.is_in(income_ids))
+        .order_by_asc(bill_tb::Column::TransactionDate)
+        .order_by_asc(bill_tb::Column::Id)
+        .all(&txn)
+        .await?;
+    for income in &incomes {
+        match_reimbursement(&txn, income).await?;
+    }
+    txn.commit().await?;
+    Ok(total)
+}
+
+/// 按列映射导入 CSV 账单，`ledger_id` 为空时导入个人账本。`dry_run` 为真时只校验并返回预览，不写入数据库；
+/// 正式导入时只要有一行校验失败就整体拒绝。
+#[handler]
+pub async fn bill_import(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let content = String::from_utf8(read_upload(req).await?)
+        .map_err(|_e| JsonErr::from_error(400, anyhow!("文件须为 UTF-8 编码")))?;
+    let content = content.trim_start_matches('\u{FEFF}');
+    let dry_run = matches!(
+        req.form::<String>("dry_run").await.as_deref(),
+        Some("1" | "true")
+    );
+    let mut mapping = HashMap::new();
+    for (key, required) in [
+        ("date_column", true),
+        ("amount_column", true),
+        ("pay_method_column", true),
+        ("tag_column", true),
+        ("comment_column", false),
+        ("direction_column", false),
+    ] {
+        match req.form::<String>(key).await.filter(|s| !s.is_empty()) {
+            Some(column) => {
+                mapping.insert(key, column);
+            }
+            None if required => {
+                res_error(400, anyhow!("未获取到列映射 {key}"))?;
+                return Ok(());
+            }
+            None => {}
+        }
+    }
+
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| JsonErr::from_error(400, anyhow!("CSV 表头解析错误：{e}")))?
+        .iter()
+        .map(|h| h.trim().to_owned())
+        .collect::<Vec<_>>();
+    let mut index = HashMap::new();
+    for (key, column) in &mapping {
+        let Some(i) = headers.iter().position(|h| h == column) else {
+            res_error(400, anyhow!("CSV 中不存在列：{column}"))?;
+            return Ok(());
+        };
+        index.insert(*key, i);
+    }
+
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let line = i + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(row_error(line, e));
+                continue;
+            }
+        };
+        let field = |key: &str| {
+            index
+                .get(key)
+                .and_then(|i| record.get(*i))
+                .map(str::trim)
+                .unwrap_or_default()
+        };
+        let Some(transaction_date) = parse_date(field("date_column")) else {
+            errors.push(row_error(line, "无效的交易日期"));
+            continue;
+        };
+        let Some(pay) = parse_amount(field("amount_column")).filter(|v| !v.is_sign_negative())
+        else {
+            errors.push(row_error(line, "无效的金额"));
+            continue;
+        };
+        let Some(direction) = parse_direction_text(field("direction_column")) else {
+            errors.push(row_error(line, "无效的收支方向"));
+            continue;
+        };
+        let account_name = field("pay_method_column");
+        if account_name.is_empty() {
+            errors.push(row_error(line, "未获取到支付账户"));
+            continue;
+        }
+        let tag_name = field("tag_column");
+        if tag_name.is_empty() {
+            errors.push(row_error(line, "未获取到交易标签"));
+            continue;
+        }
+        let comment = Some(field("comment_column"))
+            .filter(|s| !s.is_empty())
+            .map(str::to_owned);
+        rows.push(ImportRow {
+            transaction_date,
+            pay,
+            direction,
+            account_name: account_name.to_owned(),
+            tag_name: tag_name.to_owned(),
+            comment,
+            external_id: None,
+        });
+    }
+
+    let db = orm::get_dao()?;
+    let ledger_id = ledger_access(db, user_id, req.form::<i32>("ledger_id").await, true).await?;
+    let lookup = lookup(db, user_id, ledger_id, &rows).await.json_err()?;
+    let report = json!({
+        "dry_run":dry_run,
+        "valid":rows.len(),
+        "errors":errors,
+        "new_tags":lookup.new_tags,
+        "new_accounts":lookup.new_accounts
+    });
+    if dry_run {
+        res.render(Text::Json(
+            json!({
+                "status":"success",
+                "code":200,
+                "msg":{
+                    "data":report
+                }
+            })
+            .to_string(),
+        ));
+        return Ok(());
+    }
+    if !errors.is_empty() {
+        return Err(JsonErr::from_value(json!({
+            "status":"error",
+            "code":400,
+            "msg":{
+                "data":report
+            }
+        })));
+    }
+    let imported = save_rows(db, user_id, rows, lookup).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "imported":imported,
+                    "new_tags":report["new_tags"],
+                    "new_accounts":report["new_accounts"]
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
@@ -0,0 +1,586 @@
+use crate::error::*;
+use crate::orm::{
+    self,
+    model::{prelude::*, sea_orm_active_enums::LedgerRole, *},
+};
+use anyhow::anyhow;
+use argon2::password_hash::rand_core::{OsRng, RngCore};
+use chrono::{Duration, Local};
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, ConnectionTrait,
+    DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, TransactionTrait, sea_query::Expr,
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// 邀请码有效天数
+const INVITE_DAYS: i64 = 7;
+
+/// 邀请码字符集，去掉了容易混淆的 0/O、1/I/L
+const INVITE_CHARSET: &[u8] = b"23456789ABCDEFGHJKMNPQRSTUVWXYZ";
+
+/// 邀请和修改成员时可指定的角色，owner 只能是账本创建者
+fn parse_role(role: &str) -> JsonResult<LedgerRole> {
+    match role {
+        "editor" => Ok(LedgerRole::Editor),
+        "viewer" => Ok(LedgerRole::Viewer),
+        _ => Err(JsonErr::from_error(400, anyhow!("无效的成员角色"))),
+    }
+}
+
+fn invite_code() -> String {
+    let mut bytes = [0u8; 8];
+    OsRng.fill_bytes(&mut bytes);
+    bytes
+        .iter()
+        .map(|b| INVITE_CHARSET[*b as usize % INVITE_CHARSET.len()] as char)
+        .collect()
+}
+
+/// 创建账本并将 `user_id` 设为 owner，注册时创建个人账本也使用此函数
+pub(crate) async fn create_ledger(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+    name: String,
+    personal: bool,
+) -> Result<ledger_tb::Model, DbErr> {
+    let now = Local::now().naive_local();
+    let mut ledger = ledger_tb::ActiveModel::new();
+    ledger.name = Set(name);
+    ledger.owner_id = Set(user_id);
+    ledger.personal = Set(personal as i8);
+    ledger.created_time = Set(now);
+    ledger.updated_time = Set(now);
+    let ledger = ledger.insert(db).await?;
+    let mut member = ledger_member_tb::ActiveModel::new();
+    member.ledger_id = Set(ledger.id);
+    member.user_id = Set(user_id);
+    member.role = Set(LedgerRole::Owner);
+    member.created_time = Set(now);
+    member.insert(db).await?;
+    Ok(ledger)
+}
+
+/// 用户的个人账本。导入、周期账单、转账、预算等仍按个人账本记账
+pub(crate) async fn personal_ledger(db: &impl ConnectionTrait, user_id: i32) -> Result<i32, DbErr> {
+    LedgerTb::find()
+        .select_only()
+        .column(ledger_tb::Column::Id)
+        .filter(ledger_tb::Column::OwnerId.eq(user_id))
+        .filter(ledger_tb::Column::Personal.eq(1))
+        .into_tuple::<i32>()
+        .one(db)
+        .await?
+        .ok_or(DbErr::RecordNotFound(format!("用户 {user_id} 的个人账本")))
+}
+
+/// 用户在账本中的角色，不是成员时为 `None`
+pub(crate) async fn member_role(
+    db: &DatabaseConnection,
+    user_id: i32,
+    ledger_id: i32,
+) -> Result<Option<LedgerRole>, DbErr> {
+    LedgerMemberTb::find()
+        .select_only()
+        .column(ledger_member_tb::Column::Role)
+        .filter(ledger_member_tb::Column::LedgerId.eq(ledger_id))
+        .filter(ledger_member_tb::Column::UserId.eq(user_id))
+        .into_tuple::<LedgerRole>()
+        .one(db)
+        .await
+}
+
+/// 校验成员角色，`write` 为 true 时 viewer 无权操作
+pub(crate) fn check_role(role: Option<LedgerRole>, write: bool) -> JsonResult<bool> {
+    match role {
+        None => Ok(false),
+        Some(LedgerRole::Viewer) if write => {
+            Err(JsonErr::from_error(403, anyhow!("没有修改该账本的权限")))
+        }
+        Some(_) => Ok(true),
+    }
+}
+
+/// 解析请求中的账本，未指定时使用个人账本。
+/// `write` 为 true 时要求 owner 或 editor 角色
+pub(crate) async fn ledger_access(
+    db: &DatabaseConnection,
+    user_id: i32,
+    ledger_id: Option<i32>,
+    write: bool,
+) -> JsonResult<i32> {
+    let Some(ledger_id) = ledger_id else {
+        return personal_ledger(db, user_id).await.json_err();
+    };
+    if check_role(member_role(db, user_id, ledger_id).await.json_err()?, write)? {
+        Ok(ledger_id)
+    } else {
+        Err(JsonErr::from_error(400, anyhow!("无效的账本")))
+    }
+}
+
+/// 查找当前用户所在账本中的账单，不是账本成员时视为不存在
+pub(crate) async fn ledger_bill(
+    db: &DatabaseConnection,
+    user_id: i32,
+    bill_id: i32,
+    write: bool,
+) -> JsonResult<Option<bill_tb::Model>> {
+    let Some(bill) = BillTb::find_by_id(bill_id).one(db).await.json_err()? else {
+        return Ok(None);
+    };
+    let role = member_role(db, user_id, bill.ledger_id).await.json_err()?;
+    Ok(check_role(role, write)?.then_some(bill))
+}
+
+/// 查找当前用户为 owner 的账本
+async fn owned_ledger(
+    db: &DatabaseConnection,
+    user_id: i32,
+    ledger_id: i32,
+) -> JsonResult<ledger_tb::Model> {
+    match member_role(db, user_id, ledger_id).await.json_err()? {
+        Some(LedgerRole::Owner) => {}
+        Some(_) => {
+            return Err(JsonErr::from_error(
+                403,
+                anyhow!("仅账本创建者可以管理账本"),
+            ));
+        }
+        None => return Err(JsonErr::from_error(400, anyhow!("无效的账本"))),
+    }
+    LedgerTb::find_by_id(ledger_id)
+        .one(db)
+        .await
+        .json_err()?
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的账本")))
+}
+
+#[handler]
+pub async fn ledger_list(
+    _req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let db = orm::get_dao()?;
+    let list = LedgerTb::find()
+        .inner_join(LedgerMemberTb)
+        .filter(ledger_member_tb::Column::UserId.eq(user_id))
+        .column_as(ledger_member_tb::Column::Role, "role")
+        .column_as(
+            Expr::cust(
+                "(SELECT COUNT(*) FROM `ledger_member_tb` `m` WHERE `m`.`ledger_id` = `ledger_tb`.`id`)",
+            ),
+            "memberCount",
+        )
+        .order_by_desc(ledger_tb::Column::Personal)
+        .order_by_asc(ledger_tb::Column::Id)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 新建共享账本，创建者为 owner
+#[handler]
+pub async fn add_ledger(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let name = req
+        .form::<String>("name")
+        .await
+        .filter(|s| !s.is_empty())
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到账本名")))?;
+    let db = orm::get_dao()?;
+    let txn = db.begin().await.json_err()?;
+    let ledger = create_ledger(&txn, user_id, name, false).await.json_err()?;
+    txn.commit().await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "id":ledger.id
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn update_ledger(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let ledger_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账本ID")))?;
+    let name = req
+        .form::<String>("name")
+        .await
+        .filter(|s| !s.is_empty())
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到账本名")))?;
+    let db = orm::get_dao()?;
+    let mut info = owned_ledger(db, user_id, ledger_id)
+        .await?
+        .into_active_model();
+    info.name = Set(name);
+    info.updated_time = Set(Local::now().naive_local());
+    info.update(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 删除共享账本，账本中须已没有账单。标签、成员和邀请码随之删除
+#[handler]
+pub async fn del_ledger(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let ledger_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账本ID")))?;
+    let db = orm::get_dao()?;
+    let info = owned_ledger(db, user_id, ledger_id).await?;
+    if info.personal != 0 {
+        res_error(400, anyhow!("个人账本不能删除"))?;
+        return Ok(());
+    }
+    let bill_count = BillTb::find()
+        .filter(bill_tb::Column::LedgerId.eq(ledger_id))
+        .count(db)
+        .await
+        .json_err()?;
+    if bill_count != 0 {
+        res_error(400, anyhow!("该账本仍有 {bill_count} 条账单，不能删除"))?;
+        return Ok(());
+    }
+    info.into_active_model().delete(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"删除成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 生成邀请码，`role` 为加入后的角色（editor 或 viewer，默认 editor），
+/// 有效期内可多次使用
+#[handler]
+pub async fn invite_ledger(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let ledger_id = req
+        .form::<i32>("ledger_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账本ID")))?;
+    let role = match req.form::<String>("role").await {
+        Some(role) => parse_role(&role)?,
+        None => LedgerRole::Editor,
+    };
+    let db = orm::get_dao()?;
+    if owned_ledger(db, user_id, ledger_id).await?.personal != 0 {
+        res_error(400, anyhow!("个人账本不能共享"))?;
+        return Ok(());
+    }
+    let code = loop {
+        let code = invite_code();
+        if LedgerInviteTb::find()
+            .filter(ledger_invite_tb::Column::Code.eq(&code))
+            .count(db)
+            .await
+            .json_err()?
+            == 0
+        {
+            break code;
+        }
+    };
+    let now = Local::now().naive_local();
+    let expired_time = now + Duration::days(INVITE_DAYS);
+    let mut info = ledger_invite_tb::ActiveModel::new();
+    info.ledger_id = Set(ledger_id);
+    info.code = Set(code.clone());
+    info.role = Set(role);
+    info.expired_time = Set(expired_time);
+    info.created_time = Set(now);
+    info.insert(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "code":code,
+                    "expired_time":expired_time
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 使用邀请码加入账本
+#[handler]
+pub async fn join_ledger(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let code = req
+        .form::<String>("code")
+        .await
+        .map(|s| s.trim().to_ascii_uppercase())
+        .filter(|s| !s.is_empty())
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到邀请码")))?;
+    let db = orm::get_dao()?;
+    let now = Local::now().naive_local();
+    let Some(invite) = LedgerInviteTb::find()
+        .filter(ledger_invite_tb::Column::Code.eq(code))
+        .filter(ledger_invite_tb::Column::ExpiredTime.gt(now))
+        .one(db)
+        .await
+        .json_err()?
+    else {
+        res_error(400, anyhow!("邀请码无效或已过期"))?;
+        return Ok(());
+    };
+    if member_role(db, user_id, invite.ledger_id)
+        .await
+        .json_err()?
+        .is_some()
+    {
+        res_error(400, anyhow!("已是该账本成员"))?;
+        return Ok(());
+    }
+    let mut info = ledger_member_tb::ActiveModel::new();
+    info.ledger_id = Set(invite.ledger_id);
+    info.user_id = Set(user_id);
+    info.role = Set(invite.role);
+    info.created_time = Set(now);
+    info.insert(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "ledger_id":invite.ledger_id
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn member_list(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let ledger_id = req
+        .query::<i32>("ledger_id")
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账本ID")))?;
+    let db = orm::get_dao()?;
+    ledger_access(db, user_id, Some(ledger_id), false).await?;
+    let mut list = LedgerMemberTb::find()
+        .filter(ledger_member_tb::Column::LedgerId.eq(ledger_id))
+        .order_by_asc(ledger_member_tb::Column::Id)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    let accounts = UserTb::find()
+        .select_only()
+        .column(user_tb::Column::Id)
+        .column(user_tb::Column::Account)
+        .filter(user_tb::Column::Id.is_in(list.iter().filter_map(|m| m["user_id"].as_i64())))
+        .into_tuple::<(i32, String)>()
+        .all(db)
+        .await
+        .json_err()?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    for member in &mut list {
+        let account = member["user_id"]
+            .as_i64()
+            .and_then(|id| accounts.get(&(id as i32)));
+        member["account"] = json!(account);
+    }
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 修改成员角色，仅 owner 可操作
+#[handler]
+pub async fn update_member(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let ledger_id = req
+        .form::<i32>("ledger_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账本ID")))?;
+    let member_id = req
+        .form::<i32>("user_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的成员ID")))?;
+    let role = req
+        .form::<String>("role")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到成员角色")))?;
+    let role = parse_role(&role)?;
+    let db = orm::get_dao()?;
+    owned_ledger(db, user_id, ledger_id).await?;
+    if member_id == user_id {
+        res_error(400, anyhow!("不能修改账本创建者的角色"))?;
+        return Ok(());
+    }
+    let updated = LedgerMemberTb::update_many()
+        .col_expr(ledger_member_tb::Column::Role, Expr::value(role))
+        .filter(ledger_member_tb::Column::LedgerId.eq(ledger_id))
+        .filter(ledger_member_tb::Column::UserId.eq(member_id))
+        .exec(db)
+        .await
+        .json_err()?;
+    if updated.rows_affected == 0 {
+        res_error(400, anyhow!("无效的成员"))?;
+        return Ok(());
+    }
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 移除成员。owner 可移除其他成员，成员可以移除自己以退出账本；
+/// 成员已记录的账单保留在账本中
+#[handler]
+pub async fn del_member(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let ledger_id = req
+        .form::<i32>("ledger_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账本ID")))?;
+    let member_id = req.form::<i32>("user_id").await.unwrap_or(user_id);
+    let db = orm::get_dao()?;
+    if member_id == user_id {
+        match member_role(db, user_id, ledger_id).await.json_err()? {
+            Some(LedgerRole::Owner) => {
+                res_error(400, anyhow!("账本创建者不能退出账本"))?;
+                return Ok(());
+            }
+            Some(_) => {}
+            None => {
+                res_error(400, anyhow!("无效的账本"))?;
+                return Ok(());
+            }
+        }
+    } else {
+        owned_ledger(db, user_id, ledger_id).await?;
+    }
+    let deleted = LedgerMemberTb::delete_many()
+        .filter(ledger_member_tb::Column::LedgerId.eq(ledger_id))
+        .filter(ledger_member_tb::Column::UserId.eq(member_id))
+        .exec(db)
+        .await
+        .json_err()?;
+    if deleted.rows_affected == 0 {
+        res_error(400, anyhow!("无效的成员"))?;
+        return Ok(());
+    }
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"删除成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
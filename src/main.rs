@@ -3,10 +3,26 @@ use salvo::jwt_auth::{ConstDecoder, HeaderFinder, QueryFinder};
 use salvo::prelude::*;
 use serde::Deserialize;
 use tracing_appender::non_blocking::WorkerGuard;
+mod account;
+mod attachment;
 mod auth;
 mod bill;
+mod budget;
+mod claim;
+mod debt;
 mod error;
+mod export;
+mod import;
+mod ledger;
 mod orm;
+mod password;
+mod rate;
+mod recurring;
+mod share;
+mod split;
+mod statement;
+mod stats;
+mod transfer;
 use auth::{Authority, JwtClaims};
 
 #[derive(Deserialize)]
@@ -15,6 +31,12 @@ struct Config {
     database_url: String,
     secret_key: String,
     base_path: String,
+    #[serde(default)]
+    default_tags: Vec<bill::DefaultTag>,
+    #[serde(default)]
+    admin_accounts: Vec<String>,
+    #[serde(default)]
+    attachment: attachment::AttachmentConfig,
 }
 
 #[handler]
@@ -27,6 +49,10 @@ async fn main() {
     let config = Config::from_config_file("./config.toml").unwrap();
     let _trracing_guard = init_log();
     orm::init_dao(config.database_url).await;
+    bill::init_default_tags(config.default_tags);
+    rate::init_admins(config.admin_accounts);
+    attachment::init_attachment(config.attachment);
+    recurring::spawn_scheduler();
     let acceptor = TcpListener::new(config.host).bind().await;
 
     let auth_handler: JwtAuth<JwtClaims, _> =
@@ -53,16 +79,134 @@ async fn main() {
     let bill_router = bill_router.push(Router::with_path("list").get(bill::bill_list));
     let bill_router = bill_router.push(Router::with_path("add").post(bill::bill_add));
     let bill_router = bill_router.push(Router::with_path("del").post(bill::del_bill));
+    let bill_router = bill_router.push(Router::with_path("update").post(bill::update_bill));
+    let bill_router = bill_router.push(Router::with_path("stats").get(stats::bill_stats));
+    let bill_router = bill_router.push(Router::with_path("export").get(export::bill_export));
+    let bill_router = bill_router.push(Router::with_path("import").post(import::bill_import));
+    let bill_router =
+        bill_router.push(Router::with_path("import_statement").post(statement::import_statement));
+
+    let attachment_router = Router::with_path("attachment");
+    let attachment_router =
+        attachment_router.push(Router::with_path("add").post(attachment::add_attachment));
+    let attachment_router =
+        attachment_router.push(Router::with_path("list").get(attachment::attachment_list));
+    let attachment_router =
+        attachment_router.push(Router::with_path("download").get(attachment::download_attachment));
+    let attachment_router =
+        attachment_router.push(Router::with_path("del").post(attachment::del_attachment));
+    let bill_router = bill_router.push(attachment_router);
+    let split_router = Router::with_path("split");
+    let split_router = split_router.push(Router::with_path("set").post(split::set_split));
+    let split_router = split_router.push(Router::with_path("list").get(split::split_list));
+    let bill_router = bill_router.push(split_router);
+    let share_router = Router::with_path("share");
+    let share_router = share_router.push(Router::with_path("set").post(share::set_share));
+    let share_router = share_router.push(Router::with_path("list").get(share::share_list));
+    let bill_router = bill_router.push(share_router);
 
     let tag_router = Router::with_path("tag");
     let tag_router = tag_router.push(Router::with_path("add").post(bill::add_tag));
     let tag_router = tag_router.push(Router::with_path("list").post(bill::tag_list));
     let tag_router = tag_router.push(Router::with_path("del").post(bill::del_tag));
+    let tag_router = tag_router.push(Router::with_path("update").post(bill::update_tag));
+    let tag_router = tag_router.push(Router::with_path("merge").post(bill::merge_tag));
+    let tag_router = tag_router.push(Router::with_path("reorder").post(bill::reorder_tag));
+
+    let budget_router = Router::with_path("budget");
+    let budget_router = budget_router.push(Router::with_path("set").post(budget::set_budget));
+    let budget_router = budget_router.push(Router::with_path("del").post(budget::del_budget));
+    let budget_router = budget_router.push(Router::with_path("status").get(budget::budget_status));
+
+    let recurring_router = Router::with_path("recurring");
+    let recurring_router =
+        recurring_router.push(Router::with_path("list").get(recurring::recurring_list));
+    let recurring_router =
+        recurring_router.push(Router::with_path("add").post(recurring::add_recurring));
+    let recurring_router =
+        recurring_router.push(Router::with_path("update").post(recurring::update_recurring));
+    let recurring_router =
+        recurring_router.push(Router::with_path("del").post(recurring::del_recurring));
+
+    let account_router = Router::with_path("account");
+    let account_router = account_router.push(Router::with_path("list").get(account::account_list));
+    let account_router = account_router.push(Router::with_path("add").post(account::add_account));
+    let account_router =
+        account_router.push(Router::with_path("update").post(account::update_account));
+    let account_router = account_router.push(Router::with_path("del").post(account::del_account));
+    let account_router = account_router.push(Router::with_path("cycle").get(account::credit_cycle));
+
+    let transfer_router = Router::with_path("transfer");
+    let transfer_router =
+        transfer_router.push(Router::with_path("list").get(transfer::transfer_list));
+    let transfer_router =
+        transfer_router.push(Router::with_path("add").post(transfer::add_transfer));
+    let transfer_router =
+        transfer_router.push(Router::with_path("del").post(transfer::del_transfer));
+
+    let claim_router = Router::with_path("claim");
+    let claim_router = claim_router.push(Router::with_path("list").get(claim::claim_list));
+    let claim_router = claim_router.push(Router::with_path("add").post(claim::add_claim));
+    let claim_router = claim_router.push(Router::with_path("update").post(claim::update_claim));
+    let claim_router = claim_router.push(Router::with_path("del").post(claim::del_claim));
+    let claim_router = claim_router.push(Router::with_path("bills").get(claim::claim_bills));
+    let claim_router =
+        claim_router.push(Router::with_path("bills/set").post(claim::set_claim_bills));
+    let claim_router =
+        claim_router.push(Router::with_path("reimburse").post(claim::reimburse_claim));
+    let claim_router = claim_router.push(Router::with_path("export").get(claim::claim_export));
+
+    let debt_router = Router::with_path("debt");
+    let debt_router = debt_router.push(Router::with_path("list").get(debt::debt_list));
+    let debt_router = debt_router.push(Router::with_path("add").post(debt::add_debt));
+    let debt_router = debt_router.push(Router::with_path("update").post(debt::update_debt));
+    let debt_router = debt_router.push(Router::with_path("del").post(debt::del_debt));
+    let debt_router = debt_router.push(Router::with_path("summary").get(debt::debt_summary));
+    let repay_router = Router::with_path("repay");
+    let repay_router = repay_router.push(Router::with_path("add").post(debt::add_repayment));
+    let repay_router = repay_router.push(Router::with_path("list").get(debt::repayment_list));
+    let repay_router = repay_router.push(Router::with_path("del").post(debt::del_repayment));
+    let debt_router = debt_router.push(repay_router);
+
+    let rate_router = Router::with_path("rate");
+    let rate_router = rate_router.push(Router::with_path("list").get(rate::rate_list));
+    let rate_router = rate_router.push(Router::with_path("import").post(rate::import_rates));
+
+    let ledger_router = Router::with_path("ledger");
+    let ledger_router = ledger_router.push(Router::with_path("list").get(ledger::ledger_list));
+    let ledger_router = ledger_router.push(Router::with_path("add").post(ledger::add_ledger));
+    let ledger_router = ledger_router.push(Router::with_path("update").post(ledger::update_ledger));
+    let ledger_router = ledger_router.push(Router::with_path("del").post(ledger::del_ledger));
+    let ledger_router = ledger_router.push(Router::with_path("invite").post(ledger::invite_ledger));
+    let ledger_router = ledger_router.push(Router::with_path("join").post(ledger::join_ledger));
+    let member_router = Router::with_path("member");
+    let member_router = member_router.push(Router::with_path("list").get(ledger::member_list));
+    let member_router = member_router.push(Router::with_path("update").post(ledger::update_member));
+    let member_router = member_router.push(Router::with_path("del").post(ledger::del_member));
+    let ledger_router = ledger_router.push(member_router);
+    let ledger_router = ledger_router.push(Router::with_path("balance").get(share::ledger_balance));
+    let settle_router = Router::with_path("settle");
+    let settle_router = settle_router.push(Router::with_path("add").post(share::add_settlement));
+    let settle_router = settle_router.push(Router::with_path("list").get(share::settlement_list));
+    let settle_router = settle_router.push(Router::with_path("del").post(share::del_settlement));
+    let ledger_router = ledger_router.push(settle_router);
+
+    let user_router = Router::with_path("user");
+    let user_router = user_router.push(Router::with_path("currency").post(rate::set_base_currency));
 
     let auth_router = Router::with_hoop(auth_handler)
         .hoop(auth::check_auth_id)
         .push(bill_router)
-        .push(tag_router);
+        .push(tag_router)
+        .push(budget_router)
+        .push(recurring_router)
+        .push(account_router)
+        .push(transfer_router)
+        .push(debt_router)
+        .push(claim_router)
+        .push(rate_router)
+        .push(ledger_router)
+        .push(user_router);
 
     let router = router.push(auth_router);
 
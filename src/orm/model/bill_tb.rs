@@ -1,5 +1,6 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+use super::sea_orm_active_enums::Direction;
 use sea_orm::entity::prelude::*;
 
 #[derive(Clone, Debug, PartialEq, DeriveEntityModel, Eq)]
@@ -7,20 +8,79 @@ use sea_orm::entity::prelude::*;
 pub struct Model {
     #[sea_orm(primary_key)]
     pub id: i32,
-    pub tag_id: i32,
+    pub tag_id: Option<i32>,
     pub transaction_date: Date,
     #[sea_orm(column_type = "Text", nullable)]
     pub comment: Option<String>,
     pub created_time: DateTime,
     pub updated_time: DateTime,
-    pub pay_method: String,
     pub user_id: i32,
+    pub ledger_id: i32,
+    pub payer_id: Option<i32>,
     #[sea_orm(column_type = "Decimal(Some((12, 2)))", nullable)]
     pub pay: Option<Decimal>,
+    pub direction: Direction,
+    pub recurring_id: Option<i32>,
+    pub external_id: Option<String>,
+    pub account_id: i32,
+    pub transfer_id: Option<i32>,
+    pub currency: String,
+    pub reimbursable: i8,
+    pub claim_id: Option<i32>,
+    pub reimbursed_by: Option<i32>,
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(
+        belongs_to = "super::account_tb::Entity",
+        from = "Column::AccountId",
+        to = "super::account_tb::Column::Id",
+        on_update = "Restrict",
+        on_delete = "Restrict"
+    )]
+    AccountTb,
+    #[sea_orm(has_many = "super::attachment_tb::Entity")]
+    AttachmentTb,
+    #[sea_orm(has_many = "super::bill_share_tb::Entity")]
+    BillShareTb,
+    #[sea_orm(has_many = "super::bill_split_tb::Entity")]
+    BillSplitTb,
+    #[sea_orm(
+        belongs_to = "super::claim_tb::Entity",
+        from = "Column::ClaimId",
+        to = "super::claim_tb::Column::Id",
+        on_update = "Restrict",
+        on_delete = "SetNull"
+    )]
+    ClaimTb,
+    #[sea_orm(has_many = "super::debt_repayment_tb::Entity")]
+    DebtRepaymentTb,
+    #[sea_orm(
+        belongs_to = "super::ledger_tb::Entity",
+        from = "Column::LedgerId",
+        to = "super::ledger_tb::Column::Id",
+        on_update = "Restrict",
+        on_delete = "Restrict"
+    )]
+    LedgerTb,
+    #[sea_orm(
+        belongs_to = "super::recurring_tb::Entity",
+        from = "Column::RecurringId",
+        to = "super::recurring_tb::Column::Id",
+        on_update = "Restrict",
+        on_delete = "SetNull"
+    )]
+    RecurringTb,
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::ReimbursedBy",
+        to = "Column::Id",
+        on_update = "Restrict",
+        on_delete = "SetNull"
+    )]
+    SelfRef,
     #[sea_orm(
         belongs_to = "super::tag_tb::Entity",
         from = "Column::TagId",
@@ -29,6 +89,62 @@ pub enum Relation {
         on_delete = "Restrict"
     )]
     TagTb,
+    #[sea_orm(
+        belongs_to = "super::transfer_tb::Entity",
+        from = "Column::TransferId",
+        to = "super::transfer_tb::Column::Id",
+        on_update = "Restrict",
+        on_delete = "Cascade"
+    )]
+    TransferTb,
+}
+
+impl Related<super::account_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AccountTb.def()
+    }
+}
+
+impl Related<super::attachment_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::AttachmentTb.def()
+    }
+}
+
+impl Related<super::bill_share_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BillShareTb.def()
+    }
+}
+
+impl Related<super::bill_split_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BillSplitTb.def()
+    }
+}
+
+impl Related<super::claim_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::ClaimTb.def()
+    }
+}
+
+impl Related<super::debt_repayment_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::DebtRepaymentTb.def()
+    }
+}
+
+impl Related<super::ledger_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LedgerTb.def()
+    }
+}
+
+impl Related<super::recurring_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecurringTb.def()
+    }
 }
 
 impl Related<super::tag_tb::Entity> for Entity {
@@ -37,4 +153,10 @@ impl Related<super::tag_tb::Entity> for Entity {
     }
 }
 
+impl Related<super::transfer_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::TransferTb.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
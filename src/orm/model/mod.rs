@@ -2,6 +2,22 @@
 
 pub mod prelude;
 
+pub mod account_tb;
+pub mod attachment_tb;
+pub mod bill_share_tb;
+pub mod bill_split_tb;
 pub mod bill_tb;
+pub mod budget_tb;
+pub mod claim_tb;
+pub mod debt_repayment_tb;
+pub mod debt_tb;
+pub mod exchange_rate_tb;
+pub mod ledger_invite_tb;
+pub mod ledger_member_tb;
+pub mod ledger_tb;
+pub mod recurring_tb;
+pub mod sea_orm_active_enums;
+pub mod settlement_tb;
 pub mod tag_tb;
+pub mod transfer_tb;
 pub mod user_tb;
@@ -1,5 +1,20 @@
 //! `SeaORM` Entity, @generated by sea-orm-codegen 1.1.0
 
+pub use super::account_tb::Entity as AccountTb;
+pub use super::attachment_tb::Entity as AttachmentTb;
+pub use super::bill_share_tb::Entity as BillShareTb;
+pub use super::bill_split_tb::Entity as BillSplitTb;
 pub use super::bill_tb::Entity as BillTb;
+pub use super::budget_tb::Entity as BudgetTb;
+pub use super::claim_tb::Entity as ClaimTb;
+pub use super::debt_repayment_tb::Entity as DebtRepaymentTb;
+pub use super::debt_tb::Entity as DebtTb;
+pub use super::exchange_rate_tb::Entity as ExchangeRateTb;
+pub use super::ledger_invite_tb::Entity as LedgerInviteTb;
+pub use super::ledger_member_tb::Entity as LedgerMemberTb;
+pub use super::ledger_tb::Entity as LedgerTb;
+pub use super::recurring_tb::Entity as RecurringTb;
+pub use super::settlement_tb::Entity as SettlementTb;
 pub use super::tag_tb::Entity as TagTb;
+pub use super::transfer_tb::Entity as TransferTb;
 pub use super::user_tb::Entity as UserTb;
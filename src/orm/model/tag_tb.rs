@@ -11,12 +11,46 @@ pub struct Model {
     pub created_time: DateTime,
     pub updated_time: DateTime,
     pub user_id: i32,
+    pub ledger_id: i32,
+    pub parent_id: Option<i32>,
+    pub icon: Option<String>,
+    pub color: Option<String>,
+    pub position: i32,
 }
 
+#[allow(clippy::enum_variant_names)]
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
 pub enum Relation {
+    #[sea_orm(has_many = "super::bill_split_tb::Entity")]
+    BillSplitTb,
     #[sea_orm(has_many = "super::bill_tb::Entity")]
     BillTb,
+    #[sea_orm(has_many = "super::budget_tb::Entity")]
+    BudgetTb,
+    #[sea_orm(
+        belongs_to = "super::ledger_tb::Entity",
+        from = "Column::LedgerId",
+        to = "super::ledger_tb::Column::Id",
+        on_update = "Restrict",
+        on_delete = "Cascade"
+    )]
+    LedgerTb,
+    #[sea_orm(has_many = "super::recurring_tb::Entity")]
+    RecurringTb,
+    #[sea_orm(
+        belongs_to = "Entity",
+        from = "Column::ParentId",
+        to = "Column::Id",
+        on_update = "Restrict",
+        on_delete = "SetNull"
+    )]
+    SelfRef,
+}
+
+impl Related<super::bill_split_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BillSplitTb.def()
+    }
 }
 
 impl Related<super::bill_tb::Entity> for Entity {
@@ -25,4 +59,22 @@ impl Related<super::bill_tb::Entity> for Entity {
     }
 }
 
+impl Related<super::budget_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::BudgetTb.def()
+    }
+}
+
+impl Related<super::ledger_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::LedgerTb.def()
+    }
+}
+
+impl Related<super::recurring_tb::Entity> for Entity {
+    fn to() -> RelationDef {
+        Relation::RecurringTb.def()
+    }
+}
+
 impl ActiveModelBehavior for ActiveModel {}
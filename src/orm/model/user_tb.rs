@@ -11,6 +11,7 @@ pub struct Model {
     pub pass: String,
     pub created_time: DateTime,
     pub updated_time: DateTime,
+    pub base_currency: String,
 }
 
 #[derive(Copy, Clone, Debug, EnumIter, DeriveRelation)]
@@ -0,0 +1,32 @@
+use argon2::{
+    Argon2,
+    password_hash::{PasswordHash, PasswordHasher, PasswordVerifier, SaltString, rand_core::OsRng},
+};
+
+/// 密码存储采用 PHC 字符串格式（`$argon2id$v=19$...`），算法标识随哈希一同保存。
+/// 早期账号保存的是无盐的 32 位十六进制 MD5，登录成功后会被升级。
+pub fn hash(pass: &str) -> anyhow::Result<String> {
+    let salt = SaltString::generate(&mut OsRng);
+    let hashed = Argon2::default()
+        .hash_password(pass.as_bytes(), &salt)
+        .map_err(|e| anyhow::anyhow!("密码加密失败：{e}"))?;
+    Ok(hashed.to_string())
+}
+
+pub fn verify(pass: &str, stored: &str) -> bool {
+    if is_legacy(stored) {
+        return format!("{:x}", md5::compute(pass)).eq_ignore_ascii_case(stored);
+    }
+    PasswordHash::new(stored)
+        .map(|parsed| {
+            Argon2::default()
+                .verify_password(pass.as_bytes(), &parsed)
+                .is_ok()
+        })
+        .unwrap_or(false)
+}
+
+/// 是否为需要升级的旧版 MD5 密码
+pub fn is_legacy(stored: &str) -> bool {
+    !stored.starts_with('$')
+}
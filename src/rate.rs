@@ -0,0 +1,327 @@
+use crate::error::*;
+use crate::import::{parse_amount, parse_date, read_upload, row_error};
+use crate::orm::{
+    self,
+    model::{prelude::*, *},
+};
+use anyhow::anyhow;
+use chrono::{Local, NaiveDate};
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveModelBehavior,
+    ActiveValue::Set,
+    ColumnTrait, ConnectionTrait, DatabaseConnection, DbErr, EntityTrait, PaginatorTrait,
+    QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+    sea_query::{Expr, OnConflict, SimpleExpr},
+};
+use serde_json::json;
+use std::sync::OnceLock;
+
+/// 汇率表以人民币计价，人民币本身不需要汇率
+pub(crate) const RATE_BASE: &str = "CNY";
+
+/// 单次批量写入的汇率条数
+const INSERT_BATCH: usize = 500;
+
+/// 可以上传汇率的管理员账号，在 `config.toml` 的 `admin_accounts` 中配置
+static ADMIN_ACCOUNTS: OnceLock<Vec<String>> = OnceLock::new();
+
+pub fn init_admins(accounts: Vec<String>) {
+    ADMIN_ACCOUNTS
+        .set(accounts)
+        .unwrap_or_else(|_| panic!("admin accounts already initialized"));
+}
+
+/// 币种代码为三位字母，统一转为大写
+pub(crate) fn parse_currency(value: &str) -> JsonResult<String> {
+    let value = value.trim();
+    if value.len() == 3 && value.chars().all(|c| c.is_ascii_alphabetic()) {
+        Ok(value.to_ascii_uppercase())
+    } else {
+        Err(JsonErr::from_error(400, anyhow!("无效的币种：{value}")))
+    }
+}
+
+/// 币种须为人民币或汇率表中已有的币种，否则无法折算
+pub(crate) async fn ensure_currency(db: &DatabaseConnection, currency: &str) -> JsonResult<()> {
+    if currency == RATE_BASE
+        || ExchangeRateTb::find()
+            .filter(exchange_rate_tb::Column::Currency.eq(currency))
+            .count(db)
+            .await
+            .json_err()?
+            != 0
+    {
+        Ok(())
+    } else {
+        Err(JsonErr::from_error(400, anyhow!("暂无 {currency} 的汇率")))
+    }
+}
+
+/// 用户的本位币，新增账单未指定币种时使用
+pub(crate) async fn base_currency(
+    db: &impl ConnectionTrait,
+    user_id: i32,
+) -> Result<String, DbErr> {
+    Ok(UserTb::find_by_id(user_id)
+        .select_only()
+        .column(user_tb::Column::BaseCurrency)
+        .into_tuple::<String>()
+        .one(db)
+        .await?
+        .unwrap_or_else(|| RATE_BASE.to_owned()))
+}
+
+/// 某币种在账单交易日当天或之前最近一次的汇率
+fn rate_on_date(currency: &str) -> String {
+    format!(
+        "IF({currency} = '{RATE_BASE}', 1, (SELECT `r`.`rate` FROM `exchange_rate_tb` `r` \
+         WHERE `r`.`currency` = {currency} AND `r`.`rate_date` <= `bill_tb`.`transaction_date` \
+         ORDER BY `r`.`rate_date` DESC LIMIT 1))"
+    )
+}
+
+/// 账单金额折算为查看者 `viewer` 本位币的 SQL 表达式。共享账本中各成员的本位币可能不同，
+/// 统一按查看者折算，与响应中的 `base_currency` 一致。缺少汇率时结果为 NULL，不计入汇总。
+pub(crate) fn converted_pay(viewer: i32) -> SimpleExpr {
+    converted("`bill_tb`.`pay`", viewer)
+}
+
+/// 将 `amount` 按所在账单的币种折算为 `viewer` 的本位币，`amount` 须为 `bill_tb` 行上的金额
+pub(crate) fn converted(amount: &str, viewer: i32) -> SimpleExpr {
+    Expr::cust(format!(
+        "ROUND({amount} * (SELECT IF(`bill_tb`.`currency` = `u`.`base_currency`, 1, {} / {}) \
+         FROM `user_tb` `u` WHERE `u`.`id` = {viewer}), 2)",
+        rate_on_date("`bill_tb`.`currency`"),
+        rate_on_date("`u`.`base_currency`"),
+    ))
+}
+
+/// 管理员上传汇率 CSV，表头为 `currency,rate_date,rate`，
+/// `rate` 为 1 单位该币种折合人民币。同一币种同一日期重复上传即覆盖。
+#[handler]
+pub async fn import_rates(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let db = orm::get_dao()?;
+    let is_admin = UserTb::find_by_id(user_id)
+        .one(db)
+        .await
+        .json_err()?
+        .is_some_and(|user| {
+            ADMIN_ACCOUNTS
+                .get()
+                .is_some_and(|admins| admins.contains(&user.account))
+        });
+    if !is_admin {
+        res_error(403, anyhow!("仅管理员可以上传汇率"))?;
+        return Ok(());
+    }
+    let content = String::from_utf8(read_upload(req).await?)
+        .map_err(|_e| JsonErr::from_error(400, anyhow!("文件须为 UTF-8 编码")))?;
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(content.trim_start_matches('\u{FEFF}').as_bytes());
+    let headers = reader
+        .headers()
+        .map_err(|e| JsonErr::from_error(400, anyhow!("CSV 表头解析错误：{e}")))?
+        .iter()
+        .map(|h| h.trim().to_owned())
+        .collect::<Vec<_>>();
+    let column = |name: &str| headers.iter().position(|h| h == name);
+    let (Some(currency), Some(rate_date), Some(rate)) =
+        (column("currency"), column("rate_date"), column("rate"))
+    else {
+        res_error(400, anyhow!("CSV 表头须包含 currency、rate_date、rate"))?;
+        return Ok(());
+    };
+
+    let now = Local::now().naive_local();
+    let mut rates = Vec::new();
+    let mut errors = Vec::new();
+    for (i, record) in reader.records().enumerate() {
+        let line = i + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(row_error(line, e));
+                continue;
+            }
+        };
+        let field = |index: usize| record.get(index).map(str::trim).unwrap_or_default();
+        let Ok(currency) = parse_currency(field(currency)) else {
+            errors.push(row_error(line, "无效的币种"));
+            continue;
+        };
+        if currency == RATE_BASE {
+            errors.push(row_error(line, "人民币不需要汇率"));
+            continue;
+        }
+        let Some(rate_date) = parse_date(field(rate_date)) else {
+            errors.push(row_error(line, "无效的日期"));
+            continue;
+        };
+        let Some(rate) = parse_amount(field(rate)).filter(|v| v.is_sign_positive() && !v.is_zero())
+        else {
+            errors.push(row_error(line, "无效的汇率"));
+            continue;
+        };
+        let mut info = exchange_rate_tb::ActiveModel::new();
+        info.currency = Set(currency);
+        info.rate_date = Set(rate_date);
+        info.rate = Set(rate);
+        info.created_time = Set(now);
+        info.updated_time = Set(now);
+        rates.push(info);
+    }
+    if !errors.is_empty() {
+        return Err(JsonErr::from_value(json!({
+            "status":"error",
+            "code":400,
+            "msg":{
+                "data":{
+                    "valid":rates.len(),
+                    "errors":errors
+                }
+            }
+        })));
+    }
+
+    let total = rates.len();
+    let txn = db.begin().await.json_err()?;
+    while !rates.is_empty() {
+        let rest = rates.split_off(rates.len().min(INSERT_BATCH));
+        ExchangeRateTb::insert_many(rates)
+            .on_conflict(
+                OnConflict::columns([
+                    exchange_rate_tb::Column::Currency,
+                    exchange_rate_tb::Column::RateDate,
+                ])
+                .update_columns([
+                    exchange_rate_tb::Column::Rate,
+                    exchange_rate_tb::Column::UpdatedTime,
+                ])
+                .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await
+            .json_err()?;
+        rates = rest;
+    }
+    txn.commit().await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "imported":total
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn rate_list(req: &mut Request, res: &mut Response) -> JsonResult<()> {
+    let mut select = ExchangeRateTb::find();
+    if let Some(currency) = req.query::<String>("currency").filter(|s| !s.is_empty()) {
+        select = select.filter(exchange_rate_tb::Column::Currency.eq(parse_currency(&currency)?));
+    }
+    if let Some(begin) = req.query::<String>("begin") {
+        let begin = NaiveDate::parse_from_str(&begin, "%Y-%m-%d")
+            .map_err(|e| JsonErr::from_error(400, anyhow!("起始日期解析错误：{}", e)))?;
+        select = select.filter(exchange_rate_tb::Column::RateDate.gte(begin));
+    }
+    if let Some(end) = req.query::<String>("end") {
+        let end = NaiveDate::parse_from_str(&end, "%Y-%m-%d")
+            .map_err(|e| JsonErr::from_error(400, anyhow!("结束日期解析错误：{}", e)))?;
+        select = select.filter(exchange_rate_tb::Column::RateDate.lte(end));
+    }
+    let db = orm::get_dao()?;
+    let list = select
+        .order_by_asc(exchange_rate_tb::Column::Currency)
+        .order_by_desc(exchange_rate_tb::Column::RateDate)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "base":RATE_BASE,
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 设置本位币，账单列表、统计、预算和账户余额均按本位币汇总
+#[handler]
+pub async fn set_base_currency(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let currency = req
+        .form::<String>("currency")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到币种")))?;
+    let currency = parse_currency(&currency)?;
+    let db = orm::get_dao()?;
+    ensure_currency(db, &currency).await?;
+    UserTb::update_many()
+        .col_expr(user_tb::Column::BaseCurrency, Expr::value(currency))
+        .col_expr(
+            user_tb::Column::UpdatedTime,
+            Expr::value(Local::now().naive_local()),
+        )
+        .filter(user_tb::Column::Id.eq(user_id))
+        .exec(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parse_currency_normalizes_case_and_spaces() {
+        assert_eq!(parse_currency("usd").ok().as_deref(), Some("USD"));
+        assert_eq!(parse_currency(" Jpy ").ok().as_deref(), Some("JPY"));
+        assert_eq!(parse_currency("CNY").ok().as_deref(), Some("CNY"));
+    }
+
+    #[test]
+    fn parse_currency_rejects_invalid_codes() {
+        for value in ["", "US", "USDT", "U5D", "人民币", "¥¥"] {
+            assert!(parse_currency(value).is_err(), "{value}");
+        }
+    }
+}
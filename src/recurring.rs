@@ -0,0 +1,501 @@
+use crate::bill::parse_direction;
+use crate::error::*;
+use crate::ledger::personal_ledger;
+use crate::orm::{
+    self,
+    model::{
+        prelude::*,
+        sea_orm_active_enums::{Direction, Frequency},
+        *,
+    },
+};
+use crate::rate::{base_currency, ensure_currency, parse_currency};
+use anyhow::anyhow;
+use chrono::{Datelike, Days, Local, Months, NaiveDate};
+use rust_decimal::prelude::*;
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, Condition, DatabaseConnection, DbErr, EntityTrait, IntoActiveModel, QueryFilter,
+    QueryOrder, QuerySelect, TransactionTrait,
+    sea_query::{Expr, OnConflict},
+};
+use serde_json::json;
+use std::time::Duration;
+
+fn parse_frequency(frequency: &str) -> JsonResult<Frequency> {
+    match frequency {
+        "daily" => Ok(Frequency::Daily),
+        "weekly" => Ok(Frequency::Weekly),
+        "monthly" => Ok(Frequency::Monthly),
+        "yearly" => Ok(Frequency::Yearly),
+        _ => Err(JsonErr::from_error(400, anyhow!("无效的重复周期"))),
+    }
+}
+
+/// `date` 之后的下一次记账日期。按月、按年的规则始终以 `start` 为锚点计算，
+/// 避免 1 月 31 日经过 2 月后退化成每月 28 日。
+fn next_occurrence(frequency: &Frequency, start: NaiveDate, date: NaiveDate) -> Option<NaiveDate> {
+    match frequency {
+        Frequency::Daily => date.checked_add_days(Days::new(1)),
+        Frequency::Weekly => date.checked_add_days(Days::new(7)),
+        Frequency::Monthly => {
+            let months =
+                (date.year() - start.year()) * 12 + date.month() as i32 - start.month() as i32 + 1;
+            start.checked_add_months(Months::new(months as u32))
+        }
+        Frequency::Yearly => {
+            let years = date.year() - start.year() + 1;
+            start.checked_add_months(Months::new(years as u32 * 12))
+        }
+    }
+}
+
+/// 为所有到期的规则补齐账单。`(recurring_id, transaction_date)` 上有唯一索引，
+/// 重复执行或多实例同时执行都不会生成重复账单。
+pub async fn materialize_due(db: &DatabaseConnection, today: NaiveDate) -> Result<u64, DbErr> {
+    let due = RecurringTb::find()
+        .filter(recurring_tb::Column::NextDate.lte(today))
+        .filter(
+            Condition::any()
+                .add(recurring_tb::Column::EndDate.is_null())
+                .add(
+                    Expr::col(recurring_tb::Column::EndDate)
+                        .gte(Expr::col(recurring_tb::Column::NextDate)),
+                ),
+        )
+        .order_by_asc(recurring_tb::Column::Id)
+        .all(db)
+        .await?;
+    let mut inserted = 0;
+    for rule in due {
+        inserted += materialize_rule(db, &rule, today).await?;
+    }
+    Ok(inserted)
+}
+
+/// 为单条规则补齐到 `today` 为止的账单，加锁后以数据库中的规则为准
+async fn materialize_rule(
+    db: &DatabaseConnection,
+    rule: &recurring_tb::Model,
+    today: NaiveDate,
+) -> Result<u64, DbErr> {
+    let txn = db.begin().await?;
+    let Some(rule) = RecurringTb::find_by_id(rule.id)
+        .lock_exclusive()
+        .one(&txn)
+        .await?
+    else {
+        return Ok(0);
+    };
+    let mut inserted = 0;
+    let now = Local::now().naive_local();
+    let ledger_id = personal_ledger(&txn, rule.user_id).await?;
+    let mut next_date = rule.next_date;
+    while next_date <= today && rule.end_date.is_none_or(|end| next_date <= end) {
+        let mut info = bill_tb::ActiveModel::new();
+        info.comment = Set(rule.comment.clone());
+        info.pay = Set(Some(rule.pay));
+        info.account_id = Set(rule.account_id);
+        info.transaction_date = Set(next_date);
+        info.user_id = Set(rule.user_id);
+        info.ledger_id = Set(ledger_id);
+        info.tag_id = Set(Some(rule.tag_id));
+        info.direction = Set(rule.direction.clone());
+        info.currency = Set(rule.currency.clone());
+        info.recurring_id = Set(Some(rule.id));
+        info.created_time = Set(now);
+        info.updated_time = Set(now);
+        inserted += BillTb::insert(info)
+            .on_conflict(
+                OnConflict::columns([
+                    bill_tb::Column::RecurringId,
+                    bill_tb::Column::TransactionDate,
+                ])
+                .do_nothing_on([bill_tb::Column::Id])
+                .to_owned(),
+            )
+            .exec_without_returning(&txn)
+            .await?;
+        let Some(date) = next_occurrence(&rule.frequency, rule.start_date, next_date) else {
+            break;
+        };
+        next_date = date;
+    }
+    let mut rule = rule.into_active_model();
+    rule.next_date = Set(next_date);
+    rule.updated_time = Set(now);
+    rule.update(&txn).await?;
+    txn.commit().await?;
+    Ok(inserted)
+}
+
+/// 后台定时生成周期账单，启动时先执行一次，之后每小时检查一次
+pub fn spawn_scheduler() {
+    tokio::spawn(async {
+        let mut interval = tokio::time::interval(Duration::from_secs(3600));
+        loop {
+            interval.tick().await;
+            let Ok(db) = orm::get_dao() else {
+                continue;
+            };
+            match materialize_due(db, Local::now().date_naive()).await {
+                Ok(0) => {}
+                Ok(n) => tracing::info!("生成周期账单 {n} 条"),
+                Err(e) => tracing::error!("生成周期账单失败：{e}"),
+            }
+        }
+    });
+}
+
+#[handler]
+pub async fn recurring_list(
+    _req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let db = orm::get_dao()?;
+    let list = RecurringTb::find()
+        .filter(recurring_tb::Column::UserId.eq(user_id))
+        .left_join(TagTb)
+        .column_as(tag_tb::Column::Name, "tagName")
+        .left_join(AccountTb)
+        .column_as(account_tb::Column::Name, "accountName")
+        .order_by_asc(recurring_tb::Column::NextDate)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn add_recurring(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let pay = req
+        .form::<String>("pay")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到支出金额")))?;
+    let pay = Decimal::from_str(&pay)
+        .map_err(|e| JsonErr::from_error(400, anyhow!("无效的支出金额 {e}")))?;
+    let account_id = req
+        .form::<i32>("account_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到支付账户")))?;
+    let comment = req.form::<String>("comment").await;
+    let tag_id = req
+        .form::<i32>("tag_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到交易标签")))?;
+    let direction = match req.form::<String>("direction").await {
+        Some(direction) => parse_direction(&direction)?,
+        None => Direction::Expense,
+    };
+    let frequency = req
+        .form::<String>("frequency")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到重复周期")))?;
+    let frequency = parse_frequency(&frequency)?;
+    let start_date = req
+        .form::<String>("start_date")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到开始日期")))?;
+    let start_date = NaiveDate::parse_from_str(&start_date, "%Y-%m-%d")
+        .map_err(|e| JsonErr::from_error(400, anyhow!("开始日期解析错误：{}", e)))?;
+    let end_date = match req
+        .form::<String>("end_date")
+        .await
+        .filter(|s| !s.is_empty())
+    {
+        Some(end_date) => Some(
+            NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
+                .map_err(|e| JsonErr::from_error(400, anyhow!("结束日期解析错误：{}", e)))?,
+        ),
+        None => None,
+    };
+    if end_date.is_some_and(|end| end < start_date) {
+        res_error(400, anyhow!("无效的日期范围"))?;
+        return Ok(());
+    }
+
+    let db = orm::get_dao()?;
+    if TagTb::find()
+        .filter(tag_tb::Column::Id.eq(tag_id))
+        .filter(tag_tb::Column::LedgerId.eq(personal_ledger(db, user_id).await.json_err()?))
+        .one(db)
+        .await
+        .json_err()?
+        .is_none()
+    {
+        res_error(400, anyhow!("无效的标签"))?;
+        return Ok(());
+    }
+    if AccountTb::find()
+        .filter(account_tb::Column::Id.eq(account_id))
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+        .is_none()
+    {
+        res_error(400, anyhow!("无效的账户"))?;
+        return Ok(());
+    }
+    let currency = match req.form::<String>("currency").await {
+        Some(currency) => {
+            let currency = parse_currency(&currency)?;
+            ensure_currency(db, &currency).await?;
+            currency
+        }
+        None => base_currency(db, user_id).await.json_err()?,
+    };
+
+    let mut info = recurring_tb::ActiveModel::new();
+    info.user_id = Set(user_id);
+    info.tag_id = Set(tag_id);
+    info.pay = Set(pay);
+    info.direction = Set(direction);
+    info.currency = Set(currency);
+    info.account_id = Set(account_id);
+    info.comment = Set(comment);
+    info.frequency = Set(frequency);
+    info.start_date = Set(start_date);
+    info.end_date = Set(end_date);
+    info.next_date = Set(start_date);
+    let now = Local::now().naive_local();
+    info.created_time = Set(now);
+    info.updated_time = Set(now);
+    let rule = info.insert(db).await.json_err()?;
+    materialize_rule(db, &rule, Local::now().date_naive())
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"新增成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 修改只影响之后生成的账单，已生成的账单保持不变
+#[handler]
+pub async fn update_recurring(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let recurring_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的周期账单ID")))?;
+    let db = orm::get_dao()?;
+    let Some(rule) = RecurringTb::find()
+        .filter(recurring_tb::Column::Id.eq(recurring_id))
+        .filter(recurring_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+    else {
+        res_error(400, anyhow!("无效的周期账单"))?;
+        return Ok(());
+    };
+    let start_date = rule.start_date;
+    let mut info = rule.into_active_model();
+    if let Some(pay) = req.form::<String>("pay").await {
+        let pay = Decimal::from_str(&pay)
+            .map_err(|e| JsonErr::from_error(400, anyhow!("无效的支出金额 {e}")))?;
+        info.pay = Set(pay);
+    }
+    if let Some(comment) = req.form::<String>("comment").await {
+        info.comment = Set(Some(comment));
+    }
+    if let Some(direction) = req.form::<String>("direction").await {
+        info.direction = Set(parse_direction(&direction)?);
+    }
+    if let Some(currency) = req.form::<String>("currency").await {
+        let currency = parse_currency(&currency)?;
+        ensure_currency(db, &currency).await?;
+        info.currency = Set(currency);
+    }
+    if let Some(end_date) = req.form::<String>("end_date").await {
+        let end_date = if end_date.is_empty() {
+            None
+        } else {
+            Some(
+                NaiveDate::parse_from_str(&end_date, "%Y-%m-%d")
+                    .map_err(|e| JsonErr::from_error(400, anyhow!("结束日期解析错误：{}", e)))?,
+            )
+        };
+        if end_date.is_some_and(|end| end < start_date) {
+            res_error(400, anyhow!("无效的日期范围"))?;
+            return Ok(());
+        }
+        info.end_date = Set(end_date);
+    }
+    if let Some(tag_id) = req.form::<i32>("tag_id").await {
+        if TagTb::find()
+            .filter(tag_tb::Column::Id.eq(tag_id))
+            .filter(tag_tb::Column::LedgerId.eq(personal_ledger(db, user_id).await.json_err()?))
+            .one(db)
+            .await
+            .json_err()?
+            .is_none()
+        {
+            res_error(400, anyhow!("无效的标签"))?;
+            return Ok(());
+        }
+        info.tag_id = Set(tag_id);
+    }
+    if let Some(account_id) = req.form::<i32>("account_id").await {
+        if AccountTb::find()
+            .filter(account_tb::Column::Id.eq(account_id))
+            .filter(account_tb::Column::UserId.eq(user_id))
+            .one(db)
+            .await
+            .json_err()?
+            .is_none()
+        {
+            res_error(400, anyhow!("无效的账户"))?;
+            return Ok(());
+        }
+        info.account_id = Set(account_id);
+    }
+    info.updated_time = Set(Local::now().naive_local());
+    info.update(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 删除规则后已生成的账单保留，仅解除关联
+#[handler]
+pub async fn del_recurring(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let recurring_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的周期账单ID")))?;
+    let db = orm::get_dao()?;
+    if let Some(info) = RecurringTb::find()
+        .filter(recurring_tb::Column::Id.eq(recurring_id))
+        .filter(recurring_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+    {
+        let info = info.into_active_model();
+        info.delete(db).await.json_err()?;
+        res.render(Text::Json(
+            json!({
+                "status":"success",
+                "code":200,
+                "msg":"删除成功"
+            })
+            .to_string(),
+        ));
+    } else {
+        res_error(400, anyhow!("无效的周期账单"))?;
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn day(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn daily_and_weekly_step_from_date() {
+        let start = day(2024, 1, 1);
+        assert_eq!(
+            next_occurrence(&Frequency::Daily, start, day(2024, 2, 28)),
+            Some(day(2024, 2, 29))
+        );
+        assert_eq!(
+            next_occurrence(&Frequency::Weekly, start, day(2024, 12, 28)),
+            Some(day(2025, 1, 4))
+        );
+    }
+
+    #[test]
+    fn monthly_keeps_month_end_anchor() {
+        let start = day(2024, 1, 31);
+        let feb = next_occurrence(&Frequency::Monthly, start, start).unwrap();
+        assert_eq!(feb, day(2024, 2, 29));
+        let mar = next_occurrence(&Frequency::Monthly, start, feb).unwrap();
+        assert_eq!(mar, day(2024, 3, 31));
+        let apr = next_occurrence(&Frequency::Monthly, start, mar).unwrap();
+        assert_eq!(apr, day(2024, 4, 30));
+        assert_eq!(
+            next_occurrence(&Frequency::Monthly, start, apr),
+            Some(day(2024, 5, 31))
+        );
+    }
+
+    #[test]
+    fn monthly_crosses_year() {
+        let start = day(2024, 11, 30);
+        assert_eq!(
+            next_occurrence(&Frequency::Monthly, start, day(2024, 12, 30)),
+            Some(day(2025, 1, 30))
+        );
+        assert_eq!(
+            next_occurrence(&Frequency::Monthly, start, day(2025, 1, 30)),
+            Some(day(2025, 2, 28))
+        );
+    }
+
+    #[test]
+    fn yearly_keeps_leap_day_anchor() {
+        let start = day(2024, 2, 29);
+        let next = next_occurrence(&Frequency::Yearly, start, start).unwrap();
+        assert_eq!(next, day(2025, 2, 28));
+        assert_eq!(
+            next_occurrence(&Frequency::Yearly, start, day(2027, 2, 28)),
+            Some(day(2028, 2, 29))
+        );
+    }
+}
@@ -0,0 +1,631 @@
+use crate::error::*;
+use crate::ledger::{check_role, ledger_access, ledger_bill, member_role};
+use crate::orm::{
+    self,
+    model::{prelude::*, sea_orm_active_enums::Direction, *},
+};
+use crate::rate::{base_currency, parse_currency};
+use anyhow::anyhow;
+use chrono::{Local, NaiveDate};
+use rust_decimal::prelude::*;
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait,
+    ActiveValue::Set,
+    ColumnTrait, DatabaseConnection, EntityTrait, IntoActiveModel, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, TransactionTrait,
+    sea_query::{Expr, Query},
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+/// 分摊账单的付款成员，未指定时为记账人
+const PAYER: &str = "COALESCE(`bill_tb`.`payer_id`, `bill_tb`.`user_id`)";
+
+#[derive(Deserialize)]
+struct ShareMember {
+    user_id: i32,
+    shares: Option<u32>,
+    amount: Option<Decimal>,
+}
+
+/// 每位成员的份数上限
+const MAX_SHARES: u32 = 10000;
+
+/// 按份数分摊金额，精确到分，除不尽的零头按成员顺序每人多分一分
+fn distribute(total: Decimal, weights: &[u32]) -> Vec<Decimal> {
+    let cents = (total * Decimal::ONE_HUNDRED).to_i128().unwrap_or_default();
+    let sum = weights.iter().map(|w| *w as i128).sum::<i128>();
+    let mut amounts = weights
+        .iter()
+        .map(|w| cents * *w as i128 / sum)
+        .collect::<Vec<_>>();
+    let rest = cents - amounts.iter().sum::<i128>();
+    for amount in amounts.iter_mut().take(rest as usize) {
+        *amount += 1;
+    }
+    amounts
+        .into_iter()
+        .map(|c| Decimal::from_i128_with_scale(c, 2))
+        .collect()
+}
+
+/// 校验用户均为账本成员
+async fn all_members(
+    db: &DatabaseConnection,
+    ledger_id: i32,
+    user_ids: &HashSet<i32>,
+) -> JsonResult<bool> {
+    Ok(LedgerMemberTb::find()
+        .filter(ledger_member_tb::Column::LedgerId.eq(ledger_id))
+        .filter(ledger_member_tb::Column::UserId.is_in(user_ids.iter().copied()))
+        .count(db)
+        .await
+        .json_err()?
+        == user_ids.len() as u64)
+}
+
+/// 设置账单在账本成员之间的分摊，整体替换原有分摊。
+/// `payer_id` 为付款成员，不传时沿用原付款人，默认为记账人；
+/// `mode` 为 `equal`（平均）、`shares`（按份数，每人 1 到 10000 份）或 `exact`（按金额）；
+/// `members` 为 JSON 数组，如 `[{"user_id":1,"shares":2}]`，按金额时填写 `amount`。
+/// 传空数组取消分摊，付款人一并清除。
+#[handler]
+pub async fn set_share(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let bill_id = req
+        .form::<i32>("bill_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账单ID")))?;
+    let mode = req
+        .form::<String>("mode")
+        .await
+        .unwrap_or("equal".to_owned());
+    let members = req
+        .form::<String>("members")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到分摊成员")))?;
+    let members = serde_json::from_str::<Vec<ShareMember>>(&members)
+        .map_err(|e| JsonErr::from_error(400, anyhow!("分摊成员解析错误：{e}")))?;
+    let user_ids = members.iter().map(|m| m.user_id).collect::<HashSet<_>>();
+    if user_ids.len() != members.len() {
+        res_error(400, anyhow!("分摊成员重复"))?;
+        return Ok(());
+    }
+
+    let db = orm::get_dao()?;
+    let Some(bill) = ledger_bill(db, user_id, bill_id, true).await? else {
+        res_error(400, anyhow!("无效的账单"))?;
+        return Ok(());
+    };
+    if bill.transfer_id.is_some() || bill.direction != Direction::Expense {
+        res_error(400, anyhow!("只有支出账单可以分摊"))?;
+        return Ok(());
+    }
+    let pay = bill.pay.unwrap_or_default();
+    if !pay.is_sign_positive() || pay.is_zero() {
+        res_error(400, anyhow!("账单金额须大于零才能分摊"))?;
+        return Ok(());
+    }
+    let payer_id = match req.form::<i32>("payer_id").await {
+        _ if members.is_empty() => None,
+        Some(payer_id) => Some(payer_id).filter(|id| *id != bill.user_id),
+        None => bill.payer_id,
+    };
+    let shares = match mode.as_str() {
+        _ if members.is_empty() => Vec::new(),
+        "equal" => distribute(pay, &vec![1; members.len()])
+            .into_iter()
+            .map(|amount| (amount, Some(1)))
+            .collect(),
+        "shares" => {
+            let Some(weights) = members
+                .iter()
+                .map(|m| m.shares.filter(|s| (1..=MAX_SHARES).contains(s)))
+                .collect::<Option<Vec<_>>>()
+            else {
+                res_error(400, anyhow!("无效的份数"))?;
+                return Ok(());
+            };
+            distribute(pay, &weights)
+                .into_iter()
+                .zip(weights)
+                .map(|(amount, shares)| (amount, Some(shares as i32)))
+                .collect()
+        }
+        "exact" => {
+            let Some(amounts) = members
+                .iter()
+                .map(|m| m.amount.filter(|a| a.is_sign_positive() && !a.is_zero()))
+                .collect::<Option<Vec<_>>>()
+            else {
+                res_error(400, anyhow!("无效的分摊金额"))?;
+                return Ok(());
+            };
+            let total = amounts.iter().sum::<Decimal>();
+            if total != pay {
+                res_error(400, anyhow!("分摊合计 {total} 与账单金额 {pay} 不一致"))?;
+                return Ok(());
+            }
+            amounts.into_iter().map(|amount| (amount, None)).collect()
+        }
+        _ => {
+            res_error(400, anyhow!("无效的分摊方式"))?;
+            return Ok(());
+        }
+    };
+    if !all_members(db, bill.ledger_id, &user_ids).await? {
+        res_error(400, anyhow!("分摊成员须为账本成员"))?;
+        return Ok(());
+    }
+    if let Some(payer_id) = payer_id
+        && !all_members(db, bill.ledger_id, &HashSet::from([payer_id])).await?
+    {
+        res_error(400, anyhow!("付款人须为账本成员"))?;
+        return Ok(());
+    }
+
+    let txn = db.begin().await.json_err()?;
+    if payer_id != bill.payer_id {
+        let mut info = bill.into_active_model();
+        info.payer_id = Set(payer_id);
+        info.update(&txn).await.json_err()?;
+    }
+    BillShareTb::delete_many()
+        .filter(bill_share_tb::Column::BillId.eq(bill_id))
+        .exec(&txn)
+        .await
+        .json_err()?;
+    if !members.is_empty() {
+        let now = Local::now().naive_local();
+        let rows = members
+            .into_iter()
+            .zip(shares)
+            .map(|(member, (amount, shares))| {
+                let mut info = bill_share_tb::ActiveModel::new();
+                info.bill_id = Set(bill_id);
+                info.user_id = Set(member.user_id);
+                info.amount = Set(amount);
+                info.shares = Set(shares);
+                info.created_time = Set(now);
+                info
+            });
+        BillShareTb::insert_many(rows).exec(&txn).await.json_err()?;
+    }
+    txn.commit().await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn share_list(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let bill_id = req
+        .query::<i32>("bill_id")
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账单ID")))?;
+    let db = orm::get_dao()?;
+    let Some(bill) = ledger_bill(db, user_id, bill_id, false).await? else {
+        res_error(400, anyhow!("无效的账单"))?;
+        return Ok(());
+    };
+    let list = BillShareTb::find()
+        .filter(bill_share_tb::Column::BillId.eq(bill_id))
+        .order_by_asc(bill_share_tb::Column::Id)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "payer_id":bill.payer_id.unwrap_or(bill.user_id),
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 由各成员净额生成结算方案：欠款与应收分别从大到小排列后依次配对，
+/// 每笔转账至少结清一方，笔数不超过成员数减一
+fn settle_plan(net: &BTreeMap<i32, Decimal>) -> Vec<(i32, i32, Decimal)> {
+    let mut creditors = net
+        .iter()
+        .filter(|(_, v)| v.is_sign_positive() && !v.is_zero())
+        .map(|(k, v)| (*k, *v))
+        .collect::<Vec<_>>();
+    let mut debtors = net
+        .iter()
+        .filter(|(_, v)| v.is_sign_negative() && !v.is_zero())
+        .map(|(k, v)| (*k, -*v))
+        .collect::<Vec<_>>();
+    creditors.sort_by_key(|c| Reverse(c.1));
+    debtors.sort_by_key(|d| Reverse(d.1));
+    let mut plan = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < debtors.len() && j < creditors.len() {
+        let amount = debtors[i].1.min(creditors[j].1);
+        plan.push((debtors[i].0, creditors[j].0, amount));
+        debtors[i].1 -= amount;
+        creditors[j].1 -= amount;
+        if debtors[i].1.is_zero() {
+            i += 1;
+        }
+        if creditors[j].1.is_zero() {
+            j += 1;
+        }
+    }
+    plan
+}
+
+/// 账本成员之间的欠款。按币种分别汇总，正数为应收、负数为应付，
+/// 并给出结清全部欠款的转账方案
+#[handler]
+pub async fn ledger_balance(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let db = orm::get_dao()?;
+    let ledger_id = ledger_access(db, user_id, req.query::<i32>("ledger_id"), false).await?;
+
+    // 付款人垫付整笔账单，各承担者分别欠下自己的份额
+    let paid = BillTb::find()
+        .select_only()
+        .column_as(Expr::cust(PAYER), "payer_id")
+        .column(bill_tb::Column::Currency)
+        .column_as(bill_tb::Column::Pay.sum(), "amount")
+        .filter(bill_tb::Column::LedgerId.eq(ledger_id))
+        .filter(
+            bill_tb::Column::Id.in_subquery(
+                Query::select()
+                    .column(bill_share_tb::Column::BillId)
+                    .from(BillShareTb)
+                    .to_owned(),
+            ),
+        )
+        .group_by(Expr::cust(PAYER))
+        .group_by(bill_tb::Column::Currency)
+        .into_tuple::<(i32, String, Option<Decimal>)>()
+        .all(db)
+        .await
+        .json_err()?;
+    let owed = BillShareTb::find()
+        .inner_join(BillTb)
+        .select_only()
+        .column(bill_share_tb::Column::UserId)
+        .column(bill_tb::Column::Currency)
+        .column_as(bill_share_tb::Column::Amount.sum(), "amount")
+        .filter(bill_tb::Column::LedgerId.eq(ledger_id))
+        .group_by(bill_share_tb::Column::UserId)
+        .group_by(bill_tb::Column::Currency)
+        .into_tuple::<(i32, String, Option<Decimal>)>()
+        .all(db)
+        .await
+        .json_err()?;
+    // 结算时付款成员的欠款减少，收款成员的应收减少
+    let mut settled = Vec::new();
+    for (column, sign) in [
+        (settlement_tb::Column::FromUserId, Decimal::ONE),
+        (settlement_tb::Column::ToUserId, Decimal::NEGATIVE_ONE),
+    ] {
+        let rows = SettlementTb::find()
+            .select_only()
+            .column(column)
+            .column(settlement_tb::Column::Currency)
+            .column_as(settlement_tb::Column::Amount.sum(), "amount")
+            .filter(settlement_tb::Column::LedgerId.eq(ledger_id))
+            .group_by(column)
+            .group_by(settlement_tb::Column::Currency)
+            .into_tuple::<(i32, String, Option<Decimal>)>()
+            .all(db)
+            .await
+            .json_err()?;
+        settled.extend(
+            rows.into_iter()
+                .map(|(id, currency, amount)| (id, currency, amount.map(|a| a * sign))),
+        );
+    }
+
+    let mut balances = BTreeMap::<String, BTreeMap<i32, Decimal>>::new();
+    let owed = owed
+        .into_iter()
+        .map(|(id, currency, amount)| (id, currency, amount.map(|a| -a)));
+    for (id, currency, amount) in paid.into_iter().chain(owed).chain(settled) {
+        *balances.entry(currency).or_default().entry(id).or_default() += amount.unwrap_or_default();
+    }
+    let user_ids = balances
+        .values()
+        .flat_map(|net| net.keys().copied())
+        .collect::<HashSet<_>>();
+    let accounts = UserTb::find()
+        .select_only()
+        .column(user_tb::Column::Id)
+        .column(user_tb::Column::Account)
+        .filter(user_tb::Column::Id.is_in(user_ids))
+        .into_tuple::<(i32, String)>()
+        .all(db)
+        .await
+        .json_err()?
+        .into_iter()
+        .collect::<HashMap<_, _>>();
+    let list = balances
+        .into_iter()
+        .filter(|(_, net)| net.values().any(|v| !v.is_zero()))
+        .map(|(currency, net)| {
+            let plan = settle_plan(&net)
+                .into_iter()
+                .map(|(from, to, amount)| {
+                    json!({
+                        "from_user_id":from,
+                        "to_user_id":to,
+                        "amount":amount
+                    })
+                })
+                .collect::<Vec<_>>();
+            let members = net
+                .into_iter()
+                .map(|(id, amount)| {
+                    json!({
+                        "user_id":id,
+                        "account":accounts.get(&id),
+                        "amount":amount
+                    })
+                })
+                .collect::<Vec<_>>();
+            json!({
+                "currency":currency,
+                "members":members,
+                "plan":plan
+            })
+        })
+        .collect::<Vec<_>>();
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 记录成员之间的结算，`from_user_id` 默认为当前用户，币种默认为当前用户的本位币
+#[handler]
+pub async fn add_settlement(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let ledger_id = req
+        .form::<i32>("ledger_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账本ID")))?;
+    let from_user_id = req.form::<i32>("from_user_id").await.unwrap_or(user_id);
+    let to_user_id = req
+        .form::<i32>("to_user_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到收款成员")))?;
+    if from_user_id == to_user_id {
+        res_error(400, anyhow!("付款成员与收款成员不能相同"))?;
+        return Ok(());
+    }
+    let amount = req
+        .form::<String>("amount")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到结算金额")))?;
+    let amount = Decimal::from_str(&amount)
+        .ok()
+        .filter(|v| v.is_sign_positive() && !v.is_zero())
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的结算金额")))?;
+    let settle_date = match req.form::<String>("settle_date").await {
+        Some(date) => NaiveDate::parse_from_str(&date, "%Y-%m-%d")
+            .map_err(|e| JsonErr::from_error(400, anyhow!("结算日期解析错误：{}", e)))?,
+        None => Local::now().date_naive(),
+    };
+    let comment = req
+        .form::<String>("comment")
+        .await
+        .filter(|s| !s.is_empty());
+
+    let db = orm::get_dao()?;
+    ledger_access(db, user_id, Some(ledger_id), true).await?;
+    if !all_members(db, ledger_id, &HashSet::from([from_user_id, to_user_id])).await? {
+        res_error(400, anyhow!("结算双方须为账本成员"))?;
+        return Ok(());
+    }
+    let currency = match req.form::<String>("currency").await {
+        Some(currency) => parse_currency(&currency)?,
+        None => base_currency(db, user_id).await.json_err()?,
+    };
+    let mut info = settlement_tb::ActiveModel::new();
+    info.ledger_id = Set(ledger_id);
+    info.user_id = Set(user_id);
+    info.from_user_id = Set(from_user_id);
+    info.to_user_id = Set(to_user_id);
+    info.amount = Set(amount);
+    info.currency = Set(currency);
+    info.settle_date = Set(settle_date);
+    info.comment = Set(comment);
+    info.created_time = Set(Local::now().naive_local());
+    info.insert(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"新增成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn settlement_list(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let db = orm::get_dao()?;
+    let ledger_id = ledger_access(db, user_id, req.query::<i32>("ledger_id"), false).await?;
+    let list = SettlementTb::find()
+        .filter(settlement_tb::Column::LedgerId.eq(ledger_id))
+        .order_by_desc(settlement_tb::Column::SettleDate)
+        .order_by_desc(settlement_tb::Column::Id)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn del_settlement(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let settlement_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的结算ID")))?;
+    let db = orm::get_dao()?;
+    let Some(info) = SettlementTb::find_by_id(settlement_id)
+        .one(db)
+        .await
+        .json_err()?
+    else {
+        res_error(400, anyhow!("无效的结算"))?;
+        return Ok(());
+    };
+    let role = member_role(db, user_id, info.ledger_id).await.json_err()?;
+    if !check_role(role, true)? {
+        res_error(400, anyhow!("无效的结算"))?;
+        return Ok(());
+    }
+    info.into_active_model().delete(db).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"删除成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> Decimal {
+        Decimal::from_str(value).unwrap()
+    }
+
+    #[test]
+    fn distribute_gives_remainder_cents_to_first_members() {
+        assert_eq!(
+            distribute(dec("100"), &[1, 1, 1]),
+            [dec("33.34"), dec("33.33"), dec("33.33")]
+        );
+        assert_eq!(
+            distribute(dec("0.05"), &[1, 1, 1]),
+            [dec("0.02"), dec("0.02"), dec("0.01")]
+        );
+    }
+
+    #[test]
+    fn distribute_by_shares_keeps_total() {
+        assert_eq!(distribute(dec("90"), &[1, 2]), [dec("30.00"), dec("60.00")]);
+        let amounts = distribute(dec("10.01"), &[1, 2]);
+        assert_eq!(amounts, [dec("3.34"), dec("6.67")]);
+        assert_eq!(amounts.iter().sum::<Decimal>(), dec("10.01"));
+    }
+
+    #[test]
+    fn distribute_large_weights_do_not_overflow() {
+        assert_eq!(
+            distribute(dec("1000000"), &[u32::MAX, 1]),
+            [dec("1000000.00"), dec("0.00")]
+        );
+        assert_eq!(
+            distribute(dec("99999999.99"), &[MAX_SHARES, MAX_SHARES]),
+            [dec("50000000.00"), dec("49999999.99")]
+        );
+    }
+
+    #[test]
+    fn settle_plan_pairs_largest_debtor_with_largest_creditor() {
+        let net = BTreeMap::from([
+            (1, dec("70")),
+            (2, dec("30")),
+            (3, dec("-60")),
+            (4, dec("-40")),
+        ]);
+        assert_eq!(
+            settle_plan(&net),
+            [(3, 1, dec("60")), (4, 1, dec("10")), (4, 2, dec("30"))]
+        );
+    }
+
+    #[test]
+    fn settle_plan_skips_settled_members() {
+        let net = BTreeMap::from([(1, dec("50")), (2, dec("-50")), (3, Decimal::ZERO)]);
+        assert_eq!(settle_plan(&net), [(2, 1, dec("50"))]);
+        let net = BTreeMap::from([(1, Decimal::ZERO), (2, Decimal::ZERO)]);
+        assert!(settle_plan(&net).is_empty());
+    }
+}
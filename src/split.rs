@@ -0,0 +1,162 @@
+use crate::error::*;
+use crate::ledger::ledger_bill;
+use crate::orm::{
+    self,
+    model::{prelude::*, *},
+};
+use anyhow::anyhow;
+use chrono::Local;
+use rust_decimal::Decimal;
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveModelBehavior, ActiveValue::Set, ColumnTrait, EntityTrait, PaginatorTrait, QueryFilter,
+    QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde::Deserialize;
+use serde_json::json;
+use std::collections::HashSet;
+
+#[derive(Deserialize)]
+struct SplitLine {
+    tag_id: i32,
+    amount: Decimal,
+    comment: Option<String>,
+}
+
+/// 设置账单的拆分明细，整体替换原有明细。`lines` 为 JSON 数组，
+/// 如 `[{"tag_id":1,"amount":"12.50","comment":"零食"}]`，明细之和须等于账单金额；
+/// 传空数组取消拆分。
+#[handler]
+pub async fn set_split(req: &mut Request, res: &mut Response, depot: &mut Depot) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let bill_id = req
+        .form::<i32>("bill_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账单ID")))?;
+    let lines = req
+        .form::<String>("lines")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到拆分明细")))?;
+    let lines = serde_json::from_str::<Vec<SplitLine>>(&lines)
+        .map_err(|e| JsonErr::from_error(400, anyhow!("拆分明细解析错误：{e}")))?;
+    if lines.len() == 1 {
+        res_error(400, anyhow!("拆分明细至少两条"))?;
+        return Ok(());
+    }
+    if lines
+        .iter()
+        .any(|line| line.amount.is_sign_negative() || line.amount.is_zero())
+    {
+        res_error(400, anyhow!("无效的明细金额"))?;
+        return Ok(());
+    }
+
+    let db = orm::get_dao()?;
+    let Some(bill) = ledger_bill(db, user_id, bill_id, true).await? else {
+        res_error(400, anyhow!("无效的账单"))?;
+        return Ok(());
+    };
+    if bill.transfer_id.is_some() {
+        res_error(400, anyhow!("转账账单不能拆分"))?;
+        return Ok(());
+    }
+    let total = lines.iter().map(|line| line.amount).sum::<Decimal>();
+    if !lines.is_empty() && Some(total) != bill.pay {
+        res_error(
+            400,
+            anyhow!(
+                "明细合计 {total} 与账单金额 {} 不一致",
+                bill.pay.unwrap_or_default()
+            ),
+        )?;
+        return Ok(());
+    }
+    let tag_ids = lines.iter().map(|line| line.tag_id).collect::<HashSet<_>>();
+    if TagTb::find()
+        .filter(tag_tb::Column::Id.is_in(tag_ids.iter().copied()))
+        .filter(tag_tb::Column::LedgerId.eq(bill.ledger_id))
+        .count(db)
+        .await
+        .json_err()?
+        != tag_ids.len() as u64
+    {
+        res_error(400, anyhow!("无效的标签"))?;
+        return Ok(());
+    }
+
+    let txn = db.begin().await.json_err()?;
+    BillSplitTb::delete_many()
+        .filter(bill_split_tb::Column::BillId.eq(bill_id))
+        .exec(&txn)
+        .await
+        .json_err()?;
+    if !lines.is_empty() {
+        let now = Local::now().naive_local();
+        let lines = lines.into_iter().map(|line| {
+            let mut info = bill_split_tb::ActiveModel::new();
+            info.bill_id = Set(bill_id);
+            info.tag_id = Set(line.tag_id);
+            info.amount = Set(line.amount);
+            info.comment = Set(line.comment.filter(|s| !s.is_empty()));
+            info.created_time = Set(now);
+            info
+        });
+        BillSplitTb::insert_many(lines)
+            .exec(&txn)
+            .await
+            .json_err()?;
+    }
+    txn.commit().await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"修改成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn split_list(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let bill_id = req
+        .query::<i32>("bill_id")
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的账单ID")))?;
+    let db = orm::get_dao()?;
+    if ledger_bill(db, user_id, bill_id, false).await?.is_none() {
+        res_error(400, anyhow!("无效的账单"))?;
+        return Ok(());
+    }
+    let list = BillSplitTb::find()
+        .filter(bill_split_tb::Column::BillId.eq(bill_id))
+        .left_join(TagTb)
+        .column_as(tag_tb::Column::Name, "tagName")
+        .order_by_asc(bill_split_tb::Column::Id)
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
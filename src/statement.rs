@@ -0,0 +1,300 @@
+use crate::error::*;
+use crate::import::{
+    ImportRow, lookup, parse_amount, parse_date, read_upload, row_error, save_rows,
+};
+use crate::ledger::ledger_access;
+use crate::orm::{
+    self,
+    model::{prelude::*, sea_orm_active_enums::Direction, *},
+};
+use anyhow::anyhow;
+use salvo::prelude::*;
+use sea_orm::{ColumnTrait, EntityTrait, QueryFilter, QuerySelect};
+use serde_json::json;
+use std::collections::HashSet;
+
+/// 支付宝、微信支付导出的账单明细
+enum Platform {
+    Alipay,
+    Wechat,
+}
+
+impl Platform {
+    fn name(&self) -> &'static str {
+        match self {
+            Platform::Alipay => "支付宝",
+            Platform::Wechat => "微信",
+        }
+    }
+
+    fn prefix(&self) -> &'static str {
+        match self {
+            Platform::Alipay => "alipay",
+            Platform::Wechat => "wechat",
+        }
+    }
+}
+
+/// 两个平台的表头大体相同，新旧版本的列名略有差异，按候选列名依次查找
+const TIME_COLUMNS: &[&str] = &["交易时间", "交易创建时间"];
+const CATEGORY_COLUMNS: &[&str] = &["交易分类", "交易类型", "类型"];
+const COUNTERPARTY_COLUMNS: &[&str] = &["交易对方"];
+const GOODS_COLUMNS: &[&str] = &["商品说明", "商品名称", "商品"];
+const DIRECTION_COLUMNS: &[&str] = &["收/支"];
+const AMOUNT_COLUMNS: &[&str] = &["金额", "金额(元)", "金额（元）"];
+const CHANNEL_COLUMNS: &[&str] = &["收/付款方式", "支付方式"];
+const STATUS_COLUMNS: &[&str] = &["交易状态", "当前状态"];
+const ID_COLUMNS: &[&str] = &["交易订单号", "交易单号", "交易号"];
+
+/// 支付宝导出为 GBK 编码，微信为 UTF-8，优先按 UTF-8 解码
+fn decode(content: &[u8]) -> String {
+    let content = content
+        .strip_prefix("\u{FEFF}".as_bytes())
+        .unwrap_or(content);
+    match std::str::from_utf8(content) {
+        Ok(content) => content.to_owned(),
+        Err(_) => encoding_rs::GBK.decode(content).0.into_owned(),
+    }
+}
+
+#[handler]
+pub async fn import_statement(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let content = decode(&read_upload(req).await?);
+    let platform = match req.form::<String>("platform").await.as_deref() {
+        Some("alipay") => Platform::Alipay,
+        Some("wechat") => Platform::Wechat,
+        _ => {
+            res_error(400, anyhow!("无效的账单来源"))?;
+            return Ok(());
+        }
+    };
+    let dry_run = matches!(
+        req.form::<String>("dry_run").await.as_deref(),
+        Some("1" | "true")
+    );
+
+    // 明细之前是账户信息等说明文字，从表头所在行开始解析
+    let Some(offset) = content
+        .split_inclusive('\n')
+        .scan(0, |offset, line| {
+            let start = *offset;
+            *offset += line.len();
+            Some((start, line))
+        })
+        .find(|(_, line)| {
+            DIRECTION_COLUMNS.iter().any(|c| line.contains(c))
+                && TIME_COLUMNS.iter().any(|c| line.contains(c))
+        })
+        .map(|(start, _)| start)
+    else {
+        res_error(400, anyhow!("未识别的{}账单格式", platform.name()))?;
+        return Ok(());
+    };
+    let mut reader = csv::ReaderBuilder::new()
+        .flexible(true)
+        .from_reader(&content.as_bytes()[offset..]);
+    let headers = reader
+        .headers()
+        .map_err(|e| JsonErr::from_error(400, anyhow!("CSV 表头解析错误：{e}")))?
+        .iter()
+        .map(|h| h.trim().to_owned())
+        .collect::<Vec<_>>();
+    let column = |names: &[&str]| headers.iter().position(|h| names.contains(&h.as_str()));
+    let (Some(time), Some(direction), Some(amount), Some(id)) = (
+        column(TIME_COLUMNS),
+        column(DIRECTION_COLUMNS),
+        column(AMOUNT_COLUMNS),
+        column(ID_COLUMNS),
+    ) else {
+        res_error(400, anyhow!("未识别的{}账单格式", platform.name()))?;
+        return Ok(());
+    };
+    let category = column(CATEGORY_COLUMNS);
+    let counterparty = column(COUNTERPARTY_COLUMNS);
+    let goods = column(GOODS_COLUMNS);
+    let channel = column(CHANNEL_COLUMNS);
+    let status = column(STATUS_COLUMNS);
+
+    // 行号从上传文件开头计算，表头前还有若干行说明
+    let preamble = content[..offset].lines().count();
+    let mut rows = Vec::new();
+    let mut errors = Vec::new();
+    let mut skipped = 0;
+    for (i, record) in reader.records().enumerate() {
+        let line = preamble + i + 2;
+        let record = match record {
+            Ok(record) => record,
+            Err(e) => {
+                errors.push(row_error(line, e));
+                continue;
+            }
+        };
+        let field = |index: Option<usize>| {
+            index
+                .and_then(|i| record.get(i))
+                .map(str::trim)
+                .unwrap_or_default()
+        };
+        // 旧版支付宝账单末尾有以 `-` 开头的汇总说明
+        if field(Some(time)).is_empty() || field(Some(time)).starts_with('-') {
+            continue;
+        }
+        let direction = match field(Some(direction)) {
+            "支出" => Direction::Expense,
+            "收入" => Direction::Income,
+            // “不计收支”“/”多为余额转入转出，不属于收支
+            _ => {
+                skipped += 1;
+                continue;
+            }
+        };
+        let state = field(status);
+        if state.contains("关闭") || state.contains("失败") {
+            skipped += 1;
+            continue;
+        }
+        let Some(transaction_date) = parse_date(field(Some(time))) else {
+            errors.push(row_error(line, "无效的交易日期"));
+            continue;
+        };
+        let Some(pay) = parse_amount(field(Some(amount))).filter(|v| !v.is_sign_negative()) else {
+            errors.push(row_error(line, "无效的金额"));
+            continue;
+        };
+        let external_id = field(Some(id));
+        if external_id.is_empty() {
+            errors.push(row_error(line, "未获取到交易单号"));
+            continue;
+        }
+        let account_name = Some(field(channel))
+            .filter(|s| !s.is_empty() && *s != "/")
+            .unwrap_or(platform.name());
+        let tag_name = Some(field(category))
+            .filter(|s| !s.is_empty() && *s != "/")
+            .unwrap_or("未分类");
+        let comment = [field(counterparty), field(goods)]
+            .into_iter()
+            .filter(|s| !s.is_empty() && *s != "/")
+            .collect::<Vec<_>>()
+            .join(" ");
+        rows.push(ImportRow {
+            transaction_date,
+            pay,
+            direction,
+            account_name: account_name.to_owned(),
+            tag_name: tag_name.to_owned(),
+            comment: Some(comment).filter(|s| !s.is_empty()),
+            external_id: Some(format!("{}:{}", platform.prefix(), external_id)),
+        });
+    }
+
+    let db = orm::get_dao()?;
+    let mut imported_ids = BillTb::find()
+        .select_only()
+        .column(bill_tb::Column::ExternalId)
+        .filter(bill_tb::Column::UserId.eq(user_id))
+        .filter(bill_tb::Column::ExternalId.starts_with(format!("{}:", platform.prefix())))
+        .into_tuple::<Option<String>>()
+        .all(db)
+        .await
+        .json_err()?
+        .into_iter()
+        .flatten()
+        .collect::<HashSet<_>>();
+    let total = rows.len();
+    rows.retain(|row| {
+        row.external_id
+            .as_ref()
+            .is_some_and(|id| imported_ids.insert(id.clone()))
+    });
+    let duplicated = total - rows.len();
+
+    let ledger_id = ledger_access(db, user_id, req.form::<i32>("ledger_id").await, true).await?;
+    let lookup = lookup(db, user_id, ledger_id, &rows).await.json_err()?;
+    let report = json!({
+        "dry_run":dry_run,
+        "valid":rows.len(),
+        "duplicated":duplicated,
+        "skipped":skipped,
+        "errors":errors,
+        "new_tags":lookup.new_tags,
+        "new_accounts":lookup.new_accounts
+    });
+    if dry_run {
+        res.render(Text::Json(
+            json!({
+                "status":"success",
+                "code":200,
+                "msg":{
+                    "data":report
+                }
+            })
+            .to_string(),
+        ));
+        return Ok(());
+    }
+    if !errors.is_empty() {
+        return Err(JsonErr::from_value(json!({
+            "status":"error",
+            "code":400,
+            "msg":{
+                "data":report
+            }
+        })));
+    }
+    let imported = save_rows(db, user_id, rows, lookup).await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "imported":imported,
+                    "duplicated":duplicated,
+                    "skipped":skipped,
+                    "new_tags":report["new_tags"],
+                    "new_accounts":report["new_accounts"]
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rust_decimal::Decimal;
+    use std::str::FromStr;
+
+    #[test]
+    fn decode_utf8_with_bom() {
+        let content = "\u{FEFF}交易时间,金额".as_bytes();
+        assert_eq!(decode(content), "交易时间,金额");
+    }
+
+    #[test]
+    fn decode_falls_back_to_gbk() {
+        let (content, _, _) = encoding_rs::GBK.encode("支付宝交易记录,收/支");
+        assert!(std::str::from_utf8(&content).is_err());
+        assert_eq!(decode(&content), "支付宝交易记录,收/支");
+    }
+
+    #[test]
+    fn parse_amount_strips_currency_and_separators() {
+        let amount = |v: &str| Decimal::from_str(v).unwrap();
+        assert_eq!(parse_amount("¥1,234.50"), Some(amount("1234.50")));
+        assert_eq!(parse_amount(" ￥ 12.3 "), Some(amount("12.3")));
+        assert_eq!(parse_amount("-8.00"), Some(amount("-8.00")));
+        assert_eq!(parse_amount(""), None);
+        assert_eq!(parse_amount("/"), None);
+    }
+}
@@ -0,0 +1,127 @@
+use crate::bill::{
+    LINE_TAG, bill_filter, line_sum, line_tag, line_tag_in, split_lines, tag_ids_param,
+};
+use crate::error::*;
+use crate::ledger::ledger_access;
+use crate::orm::{
+    self,
+    model::{prelude::*, sea_orm_active_enums::Direction, *},
+};
+use crate::rate::base_currency;
+use anyhow::anyhow;
+use salvo::prelude::*;
+use sea_orm::{
+    ColumnTrait, JoinType, QueryFilter, QueryOrder, QuerySelect, QueryTrait, RelationTrait,
+    sea_query::{Alias, Expr, SimpleExpr},
+};
+use serde_json::json;
+
+const ROLLUP_TAG_NAME: &str = "COALESCE(`parent_tag`.`name`, `tag_tb`.`name`)";
+
+/// 明细所属标签的上级标签，没有上级即为自身。`bill_tb` 与 `bill_split_tb`
+/// 都有 `tag_id`，不能按未限定的列名分组
+fn rollup_tag_id() -> SimpleExpr {
+    Expr::cust(format!("COALESCE(`parent_tag`.`id`, {LINE_TAG})"))
+}
+
+#[handler]
+pub async fn bill_stats(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let period = req.query::<String>("period").unwrap_or("month".to_owned());
+    let format = match period.as_str() {
+        "day" => "%Y-%m-%d",
+        "week" => "%x-W%v",
+        "month" => "%Y-%m",
+        "year" => "%Y",
+        _ => {
+            res_error(400, anyhow!("无效的统计周期"))?;
+            return Ok(());
+        }
+    };
+    let group_by = req.query::<String>("group_by").unwrap_or_default();
+
+    let db = orm::get_dao()?;
+    let ledger_id = ledger_access(db, user_id, req.query::<i32>("ledger_id"), false).await?;
+
+    // 按拆分明细统计，`tag_ids` 只统计属于这些标签的明细
+    let mut select = split_lines(bill_filter(req, ledger_id)?)
+        .filter(bill_tb::Column::TransferId.is_null())
+        .select_only()
+        .column_as(
+            Expr::cust_with_values("DATE_FORMAT(`bill_tb`.`transaction_date`, ?)", [format]),
+            "period",
+        )
+        .group_by(Expr::cust("`period`"));
+    if let Some(tag_ids) = tag_ids_param(req)? {
+        select = select.filter(line_tag_in(tag_ids));
+    }
+    if matches!(group_by.as_str(), "tag" | "sub_tag") {
+        QueryTrait::query(&mut select).join(
+            JoinType::LeftJoin,
+            TagTb,
+            Expr::col((TagTb, tag_tb::Column::Id)).eq(line_tag()),
+        );
+    }
+    select = match group_by.as_str() {
+        "" => select,
+        // 子标签的金额汇总到上级标签。别名与 `bill_tb`.`tag_id` 同名，
+        // GROUP BY 会优先解析为表字段，须按表达式分组
+        "tag" => select
+            .join_as(
+                JoinType::LeftJoin,
+                tag_tb::Relation::SelfRef.def(),
+                Alias::new("parent_tag"),
+            )
+            .column_as(rollup_tag_id(), "tag_id")
+            .column_as(Expr::cust(ROLLUP_TAG_NAME), "tag_name")
+            .group_by(rollup_tag_id())
+            .group_by(Expr::cust(ROLLUP_TAG_NAME)),
+        "sub_tag" => select
+            .column_as(tag_tb::Column::Id, "tag_id")
+            .column_as(tag_tb::Column::Name, "tag_name")
+            .column_as(tag_tb::Column::ParentId, "parent_id")
+            .group_by(tag_tb::Column::Id)
+            .group_by(tag_tb::Column::Name)
+            .group_by(tag_tb::Column::ParentId),
+        "account" => select
+            .left_join(AccountTb)
+            .column(bill_tb::Column::AccountId)
+            .column_as(account_tb::Column::Name, "account_name")
+            .group_by(bill_tb::Column::AccountId)
+            .group_by(account_tb::Column::Name),
+        _ => {
+            res_error(400, anyhow!("无效的分组方式"))?;
+            return Ok(());
+        }
+    };
+    let list = select
+        .column_as(line_sum(Direction::Income, user_id), "income_amount")
+        .column_as(line_sum(Direction::Expense, user_id), "expense_amount")
+        .order_by_asc(Expr::cust("`period`"))
+        .into_json()
+        .all(db)
+        .await
+        .json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "period":period,
+                    "group_by":group_by,
+                    "base_currency":base_currency(db, user_id).await.json_err()?,
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
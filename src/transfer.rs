@@ -0,0 +1,233 @@
+use crate::attachment::{bill_attachments, remove_files};
+use crate::error::*;
+use crate::ledger::personal_ledger;
+use crate::orm::{
+    self,
+    model::{prelude::*, sea_orm_active_enums::Direction, *},
+};
+use crate::rate::base_currency;
+use anyhow::anyhow;
+use chrono::{Local, NaiveDate};
+use rust_decimal::prelude::*;
+use salvo::prelude::*;
+use sea_orm::{
+    ActiveModelBehavior, ActiveModelTrait, ActiveValue::Set, ColumnTrait, EntityTrait,
+    IntoActiveModel, PaginatorTrait, QueryFilter, QueryOrder, QuerySelect, TransactionTrait,
+};
+use serde_json::json;
+use std::collections::HashMap;
+
+/// 账户间转账：在同一个事务中写入转账记录及一对转出、转入账单
+#[handler]
+pub async fn add_transfer(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let from_account_id = req
+        .form::<i32>("from_account_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到转出账户")))?;
+    let to_account_id = req
+        .form::<i32>("to_account_id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到转入账户")))?;
+    if from_account_id == to_account_id {
+        res_error(400, anyhow!("转出与转入账户不能相同"))?;
+        return Ok(());
+    }
+    let amount = req
+        .form::<String>("amount")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到转账金额")))?;
+    let amount = Decimal::from_str(&amount)
+        .ok()
+        .filter(|v| v.is_sign_positive() && !v.is_zero())
+        .ok_or(JsonErr::from_error(400, anyhow!("无效的转账金额")))?;
+    let transaction_date = req
+        .form::<String>("transaction_date")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未获取到交易日期")))?;
+    let transaction_date = NaiveDate::parse_from_str(&transaction_date, "%Y-%m-%d")
+        .map_err(|e| JsonErr::from_error(400, anyhow!("交易日期解析错误：{}", e)))?;
+    let comment = req.form::<String>("comment").await;
+
+    let db = orm::get_dao()?;
+    if AccountTb::find()
+        .filter(account_tb::Column::Id.is_in([from_account_id, to_account_id]))
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .count(db)
+        .await
+        .json_err()?
+        != 2
+    {
+        res_error(400, anyhow!("无效的账户"))?;
+        return Ok(());
+    }
+
+    let txn = db.begin().await.json_err()?;
+    let now = Local::now().naive_local();
+    let currency = base_currency(&txn, user_id).await.json_err()?;
+    let ledger_id = personal_ledger(&txn, user_id).await.json_err()?;
+    let mut transfer = transfer_tb::ActiveModel::new();
+    transfer.user_id = Set(user_id);
+    transfer.from_account_id = Set(from_account_id);
+    transfer.to_account_id = Set(to_account_id);
+    transfer.amount = Set(amount);
+    transfer.transaction_date = Set(transaction_date);
+    transfer.comment = Set(comment.clone());
+    transfer.created_time = Set(now);
+    transfer.updated_time = Set(now);
+    let transfer = transfer.insert(&txn).await.json_err()?;
+    for (account_id, direction) in [
+        (from_account_id, Direction::Expense),
+        (to_account_id, Direction::Income),
+    ] {
+        let mut info = bill_tb::ActiveModel::new();
+        info.comment = Set(comment.clone());
+        info.pay = Set(Some(amount));
+        info.account_id = Set(account_id);
+        info.transaction_date = Set(transaction_date);
+        info.user_id = Set(user_id);
+        info.ledger_id = Set(ledger_id);
+        info.tag_id = Set(None);
+        info.direction = Set(direction);
+        info.transfer_id = Set(Some(transfer.id));
+        info.currency = Set(currency.clone());
+        info.created_time = Set(now);
+        info.updated_time = Set(now);
+        info.insert(&txn).await.json_err()?;
+    }
+    txn.commit().await.json_err()?;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"新增成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+#[handler]
+pub async fn transfer_list(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let mut select = TransferTb::find().filter(transfer_tb::Column::UserId.eq(user_id));
+    if let Some(begin) = req.query::<String>("begin") {
+        let begin = NaiveDate::parse_from_str(&begin, "%Y-%m-%d")
+            .map_err(|e| JsonErr::from_error(400, anyhow!("起始日期解析错误：{}", e)))?;
+        select = select.filter(transfer_tb::Column::TransactionDate.gte(begin));
+    }
+    if let Some(end) = req.query::<String>("end") {
+        let end = NaiveDate::parse_from_str(&end, "%Y-%m-%d")
+            .map_err(|e| JsonErr::from_error(400, anyhow!("结束日期解析错误：{}", e)))?;
+        select = select.filter(transfer_tb::Column::TransactionDate.lte(end));
+    }
+    let db = orm::get_dao()?;
+    let transfers = select
+        .order_by_desc(transfer_tb::Column::TransactionDate)
+        .order_by_desc(transfer_tb::Column::Id)
+        .all(db)
+        .await
+        .json_err()?;
+    let accounts = AccountTb::find()
+        .filter(account_tb::Column::UserId.eq(user_id))
+        .all(db)
+        .await
+        .json_err()?
+        .into_iter()
+        .map(|account| (account.id, account.name))
+        .collect::<HashMap<_, _>>();
+    let list = transfers
+        .into_iter()
+        .map(|transfer| {
+            json!({
+                "id":transfer.id,
+                "from_account_id":transfer.from_account_id,
+                "from_account_name":accounts.get(&transfer.from_account_id),
+                "to_account_id":transfer.to_account_id,
+                "to_account_name":accounts.get(&transfer.to_account_id),
+                "amount":transfer.amount,
+                "transaction_date":transfer.transaction_date,
+                "comment":transfer.comment
+            })
+        })
+        .collect::<Vec<_>>();
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":{
+                "data":{
+                    "list":list
+                }
+            }
+        })
+        .to_string(),
+    ));
+    Ok(())
+}
+
+/// 删除转账时一并删除关联的两条账单
+#[handler]
+pub async fn del_transfer(
+    req: &mut Request,
+    res: &mut Response,
+    depot: &mut Depot,
+) -> JsonResult<()> {
+    let user_id = *depot
+        .get::<i32>("user_id")
+        .map_err(|_e| JsonErr::from_error(401, anyhow!("unknown user")))?;
+    let transfer_id = req
+        .form::<i32>("id")
+        .await
+        .ok_or(JsonErr::from_error(400, anyhow!("未找到有效的转账ID")))?;
+    let db = orm::get_dao()?;
+    let Some(transfer) = TransferTb::find()
+        .filter(transfer_tb::Column::Id.eq(transfer_id))
+        .filter(transfer_tb::Column::UserId.eq(user_id))
+        .one(db)
+        .await
+        .json_err()?
+    else {
+        res_error(400, anyhow!("无效的转账"))?;
+        return Ok(());
+    };
+    let bill_ids = BillTb::find()
+        .select_only()
+        .column(bill_tb::Column::Id)
+        .filter(bill_tb::Column::TransferId.eq(transfer_id))
+        .into_tuple::<i32>()
+        .all(db)
+        .await
+        .json_err()?;
+    let attachments = bill_attachments(db, bill_ids).await.json_err()?;
+    let txn = db.begin().await.json_err()?;
+    BillTb::delete_many()
+        .filter(bill_tb::Column::TransferId.eq(transfer_id))
+        .exec(&txn)
+        .await
+        .json_err()?;
+    transfer.into_active_model().delete(&txn).await.json_err()?;
+    txn.commit().await.json_err()?;
+    remove_files(&attachments).await;
+    res.render(Text::Json(
+        json!({
+            "status":"success",
+            "code":200,
+            "msg":"删除成功"
+        })
+        .to_string(),
+    ));
+    Ok(())
+}